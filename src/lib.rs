@@ -4,7 +4,7 @@
 use arrayref::{array_mut_ref, mut_array_refs};
 use race_api::prelude::*;
 use race_proc_macro::game_handler;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 // use race_core;
 use serde::{Serialize, Deserialize};
 use rsa::{RsaPublicKey, RsaPrivateKey, PaddingScheme, PublicKey};
@@ -12,94 +12,1004 @@ use rsa::pkcs1::FromRsaPublicKey;
 use rsa::pkcs8::FromPublicKey;
 use rand::rngs::OsRng;
 use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
 use serde_json;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use ed25519_dalek::{PublicKey as VerifyKey, Signature, Verifier};
 
 const ACTION_TIMEOUT: u64 = 30_000;
 const NEXT_GAME_TIMEOUT: u64 = 15_000;
+// Upper bound for a decompressed submission, guarding against gzip bombs
+const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024;
+// Limits on the discussion attached to a single solution
+const MAX_COMMENT_LEN: usize = 280;
+const MAX_COMMENTS_PER_SOLUTION: usize = 32;
+const MIN_DIFFICULTY: u8 = 1;
+const MAX_DIFFICULTY: u8 = 10;
+// Submissions are encrypted into a single RSA block, 190 bytes with OAEP-SHA256 on a 2048-bit
+// key, which leaves room for little more than the solution and a couple of short references
+const MAX_REFERENCES: usize = 2;
+const MAX_NICKNAME_LEN: usize = 32;
+// Rounds kept in round_history, older ones are dropped first
+const MAX_ROUND_HISTORY: usize = 64;
+// Listed as the owner of anonymous solutions in place of the solver's address
+pub const ANONYMOUS_OWNER: &str = "anon";
+// Bumped whenever the checkpoint layout changes
+const CHECKPOINT_VERSION: u16 = 1;
 
 #[derive(BorshSerialize, BorshDeserialize)]
 pub enum GameEvent {
-    Submit(Vec<u8>),
+    // Nonces must increase from 1 on, a nonce not above the sender's last one marks a replay, which
+    // is ignored. Players with a signing key must send a nonce and sign it with the payload, see
+    // [[signed_submission]].
+    Submit { payload: Vec<u8>, signature: Vec<u8>, nonce: Option<u64> },
     Evaluate(Message),
+    Resync,
+    // Same as Submit, with the encrypted payload gzip-compressed
+    SubmitCompressed(Vec<u8>),
+    // Publish the plaintext of an accepted solution
+    RevealSolution { hash: String, plaintext: String },
+    // Transactor only, drop the given solution hashes from storage
+    Prune(Vec<String>),
+    // Transactor only, record the hash of a decrypted pending submission
+    MarkPendingHash { index: usize, hash: String },
+    // Let the game know the sender is still around
+    Heartbeat,
+    // Confirm a revealed solution hashes as claimed, earning the verifier's share
+    Verify(String),
+    // Transactor only, rotate the PEM encoded public key used to encrypt submissions
+    UpdatePublicKey(String),
+    // Pay the owner of a revealed solution for access to its plaintext
+    RequestSolution(String),
+    // Approve or reject a pending submission whose hash the transactor has reported
+    Vote { hash: String, approve: bool },
+    // Transactor only, mark a player as suspicious so their submissions are rejected
+    Flag(String),
+    // Transactor only, lift a previous Flag
+    Unflag(String),
+    // Transactor only, evaluate several pending submissions at once
+    EvaluateBatch(Vec<Message>),
+    // Transactor only, stop accepting submissions and evaluations until Resume
+    Pause,
+    Resume,
+    // Attach a comment to an accepted solution
+    Comment { hash: String, text: String },
+    // Hand an accepted solution over to another player, e.g. for collaborative work
+    TransferOwnership { hash: String, to: String },
+    // Evaluators only, grant or revoke the right to evaluate submissions
+    AddEvaluator(String),
+    RemoveEvaluator(String),
+    // Convert part of the sender's balance into an off-chain voucher
+    Redeem(u64),
+    // Transactor only, start over with the same players, see [[ScienceGame::reset]]
+    Reset,
+    // Vouch for an accepted solution, once per player
+    Endorse(String),
+    // Get back the fees paid for the sender's expired submissions
+    RequestRefund,
+    // Transactor only, replace the problem statement and start over on the new problem
+    SetProblemStatement(String),
+    // Set the display name shown for the sender, unique among players
+    SetNickname(String),
+    // Transactor only, fold a solution found to duplicate another into it
+    MergeSolutions { keep: String, remove: String },
+    // Rotate the sender's signing key, signed by their current one
+    RegisterSigningKey { key: Vec<u8>, signature: Vec<u8> },
+    // Pay hint_price for the sender's next hint, see [[ScienceGame::hints_delivered]]
+    RequestHint,
+    // Owner only, restrict who may request a solution, None lets anyone
+    SetAllowedViewers { hash: String, viewers: Option<Vec<String>> },
+    // Transactor only, remove a player from the game, see [[ScienceGame::kick]]
+    Kick(String),
+    // Contest a solution accepted in the current round, holding the round until resolved.
+    // Solutions from earlier rounds and seeded ones can't be challenged.
+    Challenge(String),
+    // Transactor only, settle the open challenge, dropping the solution if upheld
+    ResolveChallenge { upheld: bool },
+    // Move part of the sender's balance into the prize pool
+    Donate(u64),
 }
 
 impl CustomEvent for GameEvent {}
 
+// Fields added after the first release default when missing, so ciphertexts of the original
+// {sender, content} messages still decode. They are left out when at their default, keeping the
+// payload within one RSA block.
 #[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
 struct Message {
     sender: String,
     content: String,
+    // Claimed by the solver between MIN_DIFFICULTY and MAX_DIFFICULTY, scales the reward
+    #[serde(default = "min_difficulty", skip_serializing_if = "is_min_difficulty")]
+    difficulty: u8,
+    // Set by the transactor on evaluation, see [[full_hash_solution]]. Empty if not computed.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    full_hash: String,
+    // Prior work cited by the solution, at most MAX_REFERENCES
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    references: Vec<String>,
+    // Credit the solver without listing their address as the owner
+    #[serde(default, skip_serializing_if = "is_false")]
+    anonymous: bool,
 }
 
-// A function for message encryption
-fn encrypt_message(message: &Message, public_key: &RsaPublicKey) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+fn min_difficulty() -> u8 {
+    MIN_DIFFICULTY
+}
+
+fn is_min_difficulty(difficulty: &u8) -> bool {
+    *difficulty == MIN_DIFFICULTY
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl Message {
+    // Validate what the solver claims about an evaluated solution
+    fn check_claims(&self) -> Result<(), HandleError> {
+        if !(MIN_DIFFICULTY..=MAX_DIFFICULTY).contains(&self.difficulty) {
+            return Err(ScienceError::InvalidDifficulty.into());
+        }
+        if self.references.len() > MAX_REFERENCES {
+            return Err(ScienceError::TooManyReferences.into());
+        }
+        Ok(())
+    }
+}
+
+// The RSA padding submissions are encrypted with, configured per game
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum Padding {
+    #[default]
+    Pkcs1v15,
+    OaepSha256,
+}
+
+impl Padding {
+    // Parse the AccountData setting, an empty one keeps the original PKCS#1 v1.5 padding
+    pub fn parse(name: &str) -> Result<Self, ScienceError> {
+        match name {
+            "" | "pkcs1v15" => Ok(Padding::Pkcs1v15),
+            "oaep-sha256" => Ok(Padding::OaepSha256),
+            _ => Err(ScienceError::InvalidConfig),
+        }
+    }
+
+    // The AccountData setting for this padding, the inverse of parse
+    pub fn name(&self) -> &'static str {
+        match self {
+            Padding::Pkcs1v15 => "pkcs1v15",
+            Padding::OaepSha256 => "oaep-sha256",
+        }
+    }
+
+    fn scheme(&self) -> PaddingScheme {
+        match self {
+            Padding::Pkcs1v15 => PaddingScheme::new_pkcs1v15_encrypt(),
+            Padding::OaepSha256 => PaddingScheme::new_oaep::<Sha256>(),
+        }
+    }
+}
+
+// Why encrypting or decrypting a message failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoError {
+    // The message couldn't be serialized before encryption
+    Serialization,
+    Encryption,
+    // The ciphertext doesn't decrypt with this key and padding
+    Decryption,
+    // The plaintext isn't a serialized message
+    Deserialization,
+}
+
+impl CryptoError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CryptoError::Serialization => "message serialization failed",
+            CryptoError::Encryption => "encryption failed",
+            CryptoError::Decryption => "decryption failed",
+            CryptoError::Deserialization => "malformed plaintext",
+        }
+    }
+}
+
+impl std::fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+// Failing to encrypt is on the sender's side, while a ciphertext that doesn't decrypt to a
+// message is a malformed payload
+impl From<CryptoError> for HandleError {
+    fn from(err: CryptoError) -> Self {
+        match err {
+            CryptoError::Serialization | CryptoError::Encryption => ScienceError::EncryptionFailed.into(),
+            CryptoError::Decryption | CryptoError::Deserialization => ScienceError::MalformedPayload.into(),
+        }
+    }
+}
+
+// A function for message encryption with the game's configured padding
+fn encrypt_message(message: &Message, public_key: &RsaPublicKey, padding: Padding) -> Result<Vec<u8>, CryptoError> {
     // serialization
-    let serialized_message = serde_json::to_string(message)?;
+    let serialized_message = serde_json::to_string(message).map_err(|_| CryptoError::Serialization)?;
 
     // encryption
     let mut rng = OsRng;
-    let encrypted_message = public_key.encrypt(&mut rng, PaddingScheme::new_pkcs1v15_encrypt(), serialized_message.as_bytes())?;
+    let encrypted_message = public_key
+        .encrypt(&mut rng, padding.scheme(), serialized_message.as_bytes())
+        .map_err(|_| CryptoError::Encryption)?;
     Ok(encrypted_message)
 }
 
-// A function for message decryption
-fn decrypt_message(encrypted_message: &[u8], private_key: &RsaPrivateKey) -> Result<Message, Box<dyn std::error::Error>> {
+// A function for message decryption with the game's configured padding
+fn decrypt_message(encrypted_message: &[u8], private_key: &RsaPrivateKey, padding: Padding) -> Result<Message, CryptoError> {
     // decryption
-    let decrypted_message = private_key.decrypt(PaddingScheme::new_pkcs1v15_encrypt(), encrypted_message)?;
+    let decrypted_message = private_key
+        .decrypt(padding.scheme(), encrypted_message)
+        .map_err(|_| CryptoError::Decryption)?;
 
     // deserialization
-    let message: Message = serde_json::from_slice(&decrypted_message)?;
+    let message: Message = serde_json::from_slice(&decrypted_message).map_err(|_| CryptoError::Deserialization)?;
 
     Ok(message)
 }
 
-fn find_player(players: & mut Vec<Player>, addr: String) -> Result<& mut Player, HandleError> {
+// Whether `signature` is a valid Ed25519 signature of `payload` under `key`
+fn verify_signature(key: &[u8], payload: &[u8], signature: &[u8]) -> bool {
+    let (Ok(key), Ok(signature)) = (VerifyKey::from_bytes(key), Signature::try_from(signature)) else {
+        return false;
+    };
+    key.verify(payload, &signature).is_ok()
+}
+
+// The bytes a signed submission's signature covers: the payload followed by the little-endian nonce
+pub fn signed_submission(payload: &[u8], nonce: u64) -> Vec<u8> {
+    let mut signed = payload.to_vec();
+    signed.extend_from_slice(&nonce.to_le_bytes());
+    signed
+}
+
+// The Ed25519 key a player signs submissions with, from the hex-encoded verify_key they joined
+// with. Empty, so submissions needn't be signed, when the player joined without one. A key that
+// isn't valid hex Ed25519 is rejected rather than silently turning signing off.
+fn parse_verify_key(verify_key: &str) -> Result<Vec<u8>, HandleError> {
+    let hex = verify_key.trim();
+    if hex.is_empty() {
+        return Ok(Vec::new());
+    }
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(ScienceError::InvalidSigningKey.into());
+    }
+    let key: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect();
+    key.filter(|key| VerifyKey::from_bytes(key).is_ok())
+        .ok_or_else(|| ScienceError::InvalidSigningKey.into())
+}
+
+// The bytes a key rotation's signature covers: a domain tag, the player's address, the number of
+// keys they registered before and the new key, so a rotation can't be replayed
+pub fn signing_key_rotation(addr: &str, rotations: u64, key: &[u8]) -> Vec<u8> {
+    let mut signed = b"science-game/rotate-signing-key/".to_vec();
+    signed.extend_from_slice(addr.as_bytes());
+    signed.extend_from_slice(&rotations.to_le_bytes());
+    signed.extend_from_slice(key);
+    signed
+}
+
+// A function for encrypting a message straight from the PEM and padding published in the game state
+pub(crate) fn encrypt_for_pem(message: &Message, pem: &str, padding: Padding) -> Result<Vec<u8>, HandleError> {
+    let public_key = RsaPublicKey::from_public_key_pem(pem)
+        .map_err(|_| HandleError::from(ScienceError::InvalidPublicKey))?;
+    Ok(encrypt_message(message, &public_key, padding)?)
+}
+
+// A function for migrating a ciphertext to a new key during key rotation, keeping the game's
+// padding, e.g. for pending submissions encrypted before an UpdatePublicKey
+pub fn reencrypt(
+    old_private_key: &RsaPrivateKey,
+    new_public_key: &RsaPublicKey,
+    blob: &[u8],
+    padding: Padding,
+) -> Result<Vec<u8>, HandleError> {
+    let message = decrypt_message(blob, old_private_key, padding)?;
+    Ok(encrypt_message(&message, new_public_key, padding)?)
+}
+
+// The hash identifying an accepted solution, kept apart from player addresses so the two
+// can't be mixed up. Borrows as str, so maps keyed by it can be queried with a plain &str.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+#[serde(transparent)]
+pub struct SolutionHash(String);
+
+impl SolutionHash {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SolutionHash {
+    fn from(hash: String) -> Self {
+        SolutionHash(hash)
+    }
+}
+
+impl From<&str> for SolutionHash {
+    fn from(hash: &str) -> Self {
+        SolutionHash(hash.to_string())
+    }
+}
+
+impl From<SolutionHash> for String {
+    fn from(hash: SolutionHash) -> Self {
+        hash.0
+    }
+}
+
+impl std::borrow::Borrow<str> for SolutionHash {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SolutionHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// A function for hashing a plaintext solution, as done by the transactor during evaluation
+pub fn hash_solution(plaintext: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    plaintext.hash(&mut hasher);
+    hasher.finish().to_string()
+}
+
+// A full-length SHA-256 hex digest of a plaintext solution. The u64 from hash_solution is short
+// enough for collisions to be plausible over many solutions, so this one tells them apart.
+pub fn full_hash_solution(plaintext: &str) -> String {
+    Sha256::digest(plaintext.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// A function for gzip-compressing an encrypted payload before submission
+pub fn compress_payload(payload: &[u8]) -> Result<Vec<u8>, HandleError> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(payload)
+        .map_err(|_| HandleError::from(ScienceError::MalformedPayload))?;
+    encoder
+        .finish()
+        .map_err(|_| ScienceError::MalformedPayload.into())
+}
+
+// A function for restoring a gzip-compressed payload
+pub fn decompress_payload(payload: &[u8]) -> Result<Vec<u8>, HandleError> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(payload)
+        .take(MAX_DECOMPRESSED_SIZE + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| HandleError::from(ScienceError::MalformedPayload))?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_SIZE {
+        return Err(ScienceError::MalformedPayload.into());
+    }
+    Ok(decompressed)
+}
+
+// Errors raised by the game handler. Each variant maps to a stable message,
+// so clients can match on the error category instead of free-form text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScienceError {
+    PlayerNotFound,
+    DuplicateSolution,
+    EmptySubmission,
+    MalformedPayload,
+    RewardOverflow,
+    UnknownSolution,
+    NotSolutionOwner,
+    RevealMismatch,
+    InvalidSeededSolution,
+    NotTransactor,
+    UnknownPendingSubmission,
+    InvalidConfig,
+    NotRevealed,
+    SelfVerification,
+    AlreadyVerified,
+    EmptyProblemStatement,
+    InvalidPublicKey,
+    MalformedEvent,
+    NotEnoughPlayers,
+    InsufficientBalance,
+    SelfPurchase,
+    AlreadyPurchased,
+    BrokenInvariant,
+    VotingDisabled,
+    SelfVote,
+    AlreadyVoted,
+    StorageFull,
+    EncryptionFailed,
+    PlayerFlagged,
+    GamePaused,
+    SenderMismatch,
+    CommentTooLong,
+    TooManyComments,
+    InvalidDifficulty,
+    UnsupportedCheckpointVersion,
+    InvalidAmount,
+    PendingQueueFull,
+    SelfEndorsement,
+    AlreadyEndorsed,
+    TooManyReferences,
+    StillPending,
+    NoExpiredSubmission,
+    InvalidNickname,
+    NicknameTaken,
+    SameSolution,
+    InvalidSigningKey,
+    InvalidSignature,
+    NoHintLeft,
+    NotAllowedViewer,
+    SolutionDisputed,
+    NotChallengeable,
+    SelfChallenge,
+    NoDispute,
+    MissingSigningKey,
+    MissingNonce,
+    SubmissionExpired,
+    ReservedAddress,
+}
+
+impl ScienceError {
+    // A stable machine-readable code for front ends. Variants are only ever appended,
+    // so the codes of existing ones never change.
+    pub fn code(&self) -> u16 {
+        *self as u16
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScienceError::PlayerNotFound => "player not found",
+            ScienceError::DuplicateSolution => "duplicate solution",
+            ScienceError::EmptySubmission => "empty submission",
+            ScienceError::MalformedPayload => "malformed payload",
+            ScienceError::RewardOverflow => "reward overflow",
+            ScienceError::UnknownSolution => "unknown solution",
+            ScienceError::NotSolutionOwner => "not solution owner",
+            ScienceError::RevealMismatch => "reveal mismatch",
+            ScienceError::InvalidSeededSolution => "invalid seeded solution",
+            ScienceError::NotTransactor => "not transactor",
+            ScienceError::UnknownPendingSubmission => "unknown pending submission",
+            ScienceError::InvalidConfig => "invalid config",
+            ScienceError::NotRevealed => "solution not revealed",
+            ScienceError::SelfVerification => "cannot verify own solution",
+            ScienceError::AlreadyVerified => "already verified",
+            ScienceError::EmptyProblemStatement => "empty problem statement",
+            ScienceError::InvalidPublicKey => "invalid public key",
+            ScienceError::MalformedEvent => "malformed game event",
+            ScienceError::NotEnoughPlayers => "not enough players",
+            ScienceError::InsufficientBalance => "insufficient balance",
+            ScienceError::SelfPurchase => "cannot purchase own solution",
+            ScienceError::AlreadyPurchased => "already purchased",
+            ScienceError::BrokenInvariant => "broken invariant",
+            ScienceError::VotingDisabled => "voting disabled",
+            ScienceError::SelfVote => "cannot vote on own solution",
+            ScienceError::AlreadyVoted => "already voted",
+            ScienceError::StorageFull => "solution storage full",
+            ScienceError::EncryptionFailed => "encryption failed",
+            ScienceError::PlayerFlagged => "player flagged",
+            ScienceError::GamePaused => "game paused",
+            ScienceError::SenderMismatch => "sender mismatch",
+            ScienceError::CommentTooLong => "comment too long",
+            ScienceError::TooManyComments => "too many comments",
+            ScienceError::InvalidDifficulty => "invalid difficulty",
+            ScienceError::UnsupportedCheckpointVersion => "unsupported checkpoint version",
+            ScienceError::InvalidAmount => "invalid amount",
+            ScienceError::PendingQueueFull => "pending queue full",
+            ScienceError::SelfEndorsement => "cannot endorse own solution",
+            ScienceError::AlreadyEndorsed => "already endorsed",
+            ScienceError::TooManyReferences => "too many references",
+            ScienceError::StillPending => "submission still pending",
+            ScienceError::NoExpiredSubmission => "no expired submission",
+            ScienceError::InvalidNickname => "invalid nickname",
+            ScienceError::NicknameTaken => "nickname taken",
+            ScienceError::SameSolution => "cannot merge a solution with itself",
+            ScienceError::InvalidSigningKey => "invalid signing key",
+            ScienceError::InvalidSignature => "invalid signature",
+            ScienceError::NoHintLeft => "no hint left",
+            ScienceError::NotAllowedViewer => "not allowed to view solution",
+            ScienceError::SolutionDisputed => "solution disputed",
+            ScienceError::NotChallengeable => "solution not challengeable",
+            ScienceError::SelfChallenge => "cannot challenge own solution",
+            ScienceError::NoDispute => "no open dispute",
+            ScienceError::MissingSigningKey => "no signing key registered",
+            ScienceError::MissingNonce => "missing nonce",
+            ScienceError::SubmissionExpired => "submission expired",
+            ScienceError::ReservedAddress => "reserved address",
+        }
+    }
+}
+
+impl std::fmt::Display for ScienceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<ScienceError> for HandleError {
+    fn from(err: ScienceError) -> Self {
+        HandleError::Custom(err.to_string())
+    }
+}
+
+// Addresses are stored trimmed, and compared ignoring surrounding whitespace, so the same
+// account is always recognized regardless of how a client formats it. ASCII case is only
+// ignored on chains with case-insensitive addresses, see [[AccountData::case_insensitive_addrs]].
+fn normalize_addr(addr: &str) -> String {
+    addr.trim().to_string()
+}
+
+fn same_addr(a: &str, b: &str, case_insensitive: bool) -> bool {
+    let (a, b) = (a.trim(), b.trim());
+    if case_insensitive {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+fn find_player(players: & mut Vec<Player>, addr: String, case_insensitive: bool) -> Result<& mut Player, HandleError> {
     for player in players.iter_mut() {
-        if player.addr == addr {
+        if same_addr(&player.addr, &addr, case_insensitive) {
             return Ok(player);
         }
     }
-    return Err(HandleError::Custom("Player not found".to_string()));
+    Err(ScienceError::PlayerNotFound.into())
 }
 
-// #[derive(BorshDeserialize, BorshSerialize)]
-#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize)]
+// Seeded solutions may credit addresses that never joined the game, so owners are only
+// normalized here. Empty hashes or owners, and hashes that collide once trimmed, are rejected.
+fn normalize_seeded_solutions(
+    seeded: HashMap<String, String>,
+) -> Result<HashMap<SolutionHash, String>, HandleError> {
+    let mut solutions = HashMap::with_capacity(seeded.len());
+    for (hash, owner) in seeded.into_iter() {
+        let hash = hash.trim();
+        let owner = normalize_addr(&owner);
+        if hash.is_empty() || owner.is_empty() || solutions.insert(hash.into(), owner).is_some() {
+            return Err(ScienceError::InvalidSeededSolution.into());
+        }
+    }
+    Ok(solutions)
+}
+
+// Fields missing from older account blobs fall back to their defaults, for both serde and
+// Borsh, see the BorshDeserialize impl below.
+#[derive(Default, Serialize, Deserialize, BorshSerialize)]
+#[serde(default)]
 pub struct AccountData {
     pub coin_assigned: u64,
     pub public_key: String,
     pub encrypt_solutions: HashMap<String, String>,
+    // Percentage of coin_assigned held back for the first player verifying a revealed solution
+    pub verifier_share: u8,
+    pub problem_statement: String,
+    // Submissions are only accepted once this many players have joined
+    pub min_players: usize,
+    // Charged on every submission and refunded when the solution is accepted
+    pub submission_fee: u64,
+    // Paid to a solution's owner by players requesting its revealed plaintext
+    pub solution_price: u64,
+    // Votes needed to accept or reject a pending submission, zero disables voting
+    pub vote_quorum: u32,
+    // Upper bound on stored solutions, zero means unbounded. See [[ScienceGame::storage_full]].
+    pub max_stored_solutions: usize,
+    // The asset rewards are denominated in, carried into every settlement
+    pub asset_id: String,
+    // Upper bound on a reward after scaling by difficulty, zero means uncapped
+    pub max_reward: u64,
+    // Credited once, on top of the reward, to whoever gets the first solution accepted
+    pub first_solver_bonus: u64,
+    // Deducted from a submitter whose solution turns out to be a duplicate, down to zero
+    pub duplicate_penalty: u64,
+    // Addresses allowed to evaluate submissions besides the transactor
+    pub evaluators: Vec<String>,
+    // Upper bound on pending submissions, zero means unbounded
+    pub max_pending: usize,
+    // "pkcs1v15" (the default) or "oaep-sha256", see [[Padding]]
+    pub padding: String,
+    // Forfeited submission fees go to the prize pool instead of being burned
+    pub recycle_fees: bool,
+    // Submissions made this soon after the game starts can't win the first solver bonus
+    pub start_grace_ms: u64,
+    // Hints for the problem, handed out to each player in order as they pay for them
+    pub hints: Vec<String>,
+    pub hint_price: u64,
+    // Balance needed to approve or reject a pending submission when votes are weighted by
+    // balance, zero keeps one vote per player. See [[ScienceGame::vote_weights]].
+    pub vote_weight_threshold: u64,
+    // Make room for players syncing into a full game by evicting the lowest-balance player,
+    // see [[ScienceGame::evict]]
+    pub evict_when_full: bool,
+    // Paid out of the prize pool to whoever evaluates an accepted solution, as far as it lasts
+    pub evaluator_fee: u64,
+    // A kicked player's balance goes to the prize pool instead of being settled to them
+    pub forfeit_kicked_balance: bool,
+    // Compare addresses ignoring ASCII case, for chains whose addresses are case-insensitive,
+    // e.g. hex ones. Leave unset for case-sensitive ones such as Solana's base58 addresses.
+    pub case_insensitive_addrs: bool,
+}
+
+// Read a field appended to AccountData after the original release, keeping the default when
+// an older blob ends before it
+fn read_appended<T: BorshDeserialize>(buf: &mut &[u8], field: &mut T) -> borsh::io::Result<()> {
+    if !buf.is_empty() {
+        *field = T::deserialize_reader(buf)?;
+    }
+    Ok(())
+}
+
+// Borsh has no notion of optional fields, so only the original fields are required and the
+// appended ones are read while the blob lasts. New fields must be appended at the end.
+impl BorshDeserialize for AccountData {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> borsh::io::Result<Self> {
+        let mut data = AccountData {
+            coin_assigned: u64::deserialize_reader(reader)?,
+            public_key: String::deserialize_reader(reader)?,
+            encrypt_solutions: HashMap::deserialize_reader(reader)?,
+            ..Default::default()
+        };
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest)?;
+        let buf = &mut rest.as_slice();
+        read_appended(buf, &mut data.verifier_share)?;
+        read_appended(buf, &mut data.problem_statement)?;
+        read_appended(buf, &mut data.min_players)?;
+        read_appended(buf, &mut data.submission_fee)?;
+        read_appended(buf, &mut data.solution_price)?;
+        read_appended(buf, &mut data.vote_quorum)?;
+        read_appended(buf, &mut data.max_stored_solutions)?;
+        read_appended(buf, &mut data.asset_id)?;
+        read_appended(buf, &mut data.max_reward)?;
+        read_appended(buf, &mut data.first_solver_bonus)?;
+        read_appended(buf, &mut data.duplicate_penalty)?;
+        read_appended(buf, &mut data.evaluators)?;
+        read_appended(buf, &mut data.max_pending)?;
+        read_appended(buf, &mut data.padding)?;
+        read_appended(buf, &mut data.recycle_fees)?;
+        read_appended(buf, &mut data.start_grace_ms)?;
+        read_appended(buf, &mut data.hints)?;
+        read_appended(buf, &mut data.hint_price)?;
+        read_appended(buf, &mut data.vote_weight_threshold)?;
+        read_appended(buf, &mut data.evict_when_full)?;
+        read_appended(buf, &mut data.evaluator_fee)?;
+        read_appended(buf, &mut data.forfeit_kicked_balance)?;
+        read_appended(buf, &mut data.case_insensitive_addrs)?;
+        Ok(data)
+    }
 }
 
-#[derive(Default, Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Serialize, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum GameStage {
     #[default]
     Waiting,
     Submitted,
     Evaluated, 
+    // A solution accepted in the round is challenged, see [[GameEvent::Challenge]]
+    Disputed,
+}
+
+impl GameStage {
+    // A stable name for front ends, matching the serialized form
+    pub fn stage_name(&self) -> &'static str {
+        match self {
+            GameStage::Waiting => "waiting",
+            GameStage::Submitted => "submitted",
+            GameStage::Evaluated => "evaluated",
+            GameStage::Disputed => "disputed",
+        }
+    }
+}
+
+impl std::fmt::Display for GameStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.stage_name())
+    }
 }
 
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(Default, BorshSerialize, BorshDeserialize)]
 pub struct Player {
     pub addr: String,
     pub balance: u64,
-    pub local_encrypt_solutions: HashMap<String, String>,
+    pub local_encrypt_solutions: HashMap<SolutionHash, String>,
+    pub last_seen: u64,
+    pub flagged: bool,
+    // A display name for UIs, unique among the players
+    pub nickname: Option<String>,
+    // Ed25519 public key submissions must be signed with, empty when not registered
+    pub signing_key: Vec<u8>,
+    // Number of hints bought so far, the next one bought is hints[hints_used]
+    pub hints_used: usize,
+    // Number of signing keys registered with RegisterSigningKey, see [[signing_key_rotation]]
+    pub key_rotations: u64,
+    // Highest submission nonce queued so far, to recognize replays
+    pub last_nonce: u64,
+}
+
+// An encrypted solution waiting for evaluation. The hash is only known once
+// the transactor has decrypted the payload and reported it.
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize)]
+pub struct PendingSubmission {
+    pub sender: String,
+    pub payload: Vec<u8>,
+    pub hash: Option<String>,
+    // The submission fee paid, refunded to the sender on acceptance
+    pub fee: u64,
+    pub submitted_at: u64,
+}
+
+impl PendingSubmission {
+    fn order_key(&self) -> (u64, &str) {
+        (self.submitted_at, &self.sender)
+    }
+}
+
+// A pending submission dropped because it wasn't evaluated in time, kept so the
+// submitter can find out what happened to it.
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize)]
+pub struct ExpiredSubmission {
+    pub sender: String,
+    pub fee: u64,
+    pub submitted_at: u64,
+}
+
+// A reward credited while handling the latest event, for the transactor to settle on chain
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Settlement {
+    pub addr: String,
+    pub amount: u64,
+    pub asset_id: String,
+}
+
+// A submission dropped while handling the latest event, addressed to its submitter
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Rejection {
+    pub addr: String,
+    // See [[ScienceError::code]]
+    pub code: u16,
+}
+
+// A voucher issued by the latest event, for an integration to honor off chain
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize)]
+pub struct Voucher {
+    pub addr: String,
+    pub amount: u64,
+    // Derived from the address and the game's voucher nonce, see [[voucher_id]]
+    pub id: String,
+}
+
+// A deterministic voucher identifier, so integrations can recompute it from the event log. The
+// SHA-256 hex digest of "addr:nonce", unlike hash_solution which may change between Rust releases.
+pub fn voucher_id(addr: &str, nonce: u64) -> String {
+    full_hash_solution(&format!("{}:{}", addr, nonce))
+}
+
+// A hint bought by the latest event, addressed to its buyer
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize)]
+pub struct HintDelivery {
+    pub addr: String,
+    pub hint: String,
+}
+
+// A solution newly accepted by the latest event, for real-time subscribers
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize)]
+pub struct AcceptedSolution {
+    pub hash: String,
+    pub solver: String,
+    // Credited to the solver, excluding the verifier's share and any first solver bonus
+    pub reward: u64,
+}
+
+// The settings of a game in one place, for front ends
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Clone, Serialize)]
+pub struct GameConfig {
+    pub coin_assigned: u64,
+    pub problem_statement: String,
+    pub verifier_share: u8,
+    pub min_players: usize,
+    pub submission_fee: u64,
+    pub solution_price: u64,
+    pub vote_quorum: u32,
+    pub max_stored_solutions: usize,
+    pub max_pending: usize,
+    pub max_reward: u64,
+    pub first_solver_bonus: u64,
+    pub duplicate_penalty: u64,
+    pub asset_id: String,
+    pub padding: String,
+    pub recycle_fees: bool,
+    pub start_grace_ms: u64,
+    pub hint_price: u64,
+    pub vote_weight_threshold: u64,
+    pub max_players: usize,
+    pub evict_when_full: bool,
+    pub evaluator_fee: u64,
+    pub forfeit_kicked_balance: bool,
+    pub case_insensitive_addrs: bool,
+    // In milliseconds
+    pub action_timeout: u64,
+    pub next_game_timeout: u64,
+}
+
+// What a single player needs to see of the state. Other players' balances and caches, and
+// the owners of the solutions, are left out.
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Default, Serialize)]
+pub struct PublicView {
+    pub addr: String,
+    // None when the address hasn't joined
+    pub balance: Option<u64>,
+    // Hashes of the accepted solutions, sorted
+    pub solutions: Vec<String>,
+    pub stage: GameStage,
 }
 
 #[game_handler]
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(Default, BorshSerialize, BorshDeserialize)]
 pub struct ScienceGame {
     pub players: Vec<Player>,
     pub stage: GameStage,
     pub coin_assigned: u64,
     pub public_key: String,
-    pub encrypt_solutions: HashMap<String, String>,
-    pub tmp_solutions: VecDeque<Vec<u8>>
+    pub encrypt_solutions: HashMap<SolutionHash, String>,
+    pub tmp_solutions: VecDeque<PendingSubmission>,
+    pub total_distributed: u64,
+    pub revealed: HashMap<String, String>,
+    // Hashes seeded from the account data, whose owners need not have joined
+    pub seeded_hashes: HashSet<String>,
+    pub transactor_addr: Option<String>,
+    pub verifier_share: u8,
+    // Rewards held back for the first verifier, keyed by solution hash
+    pub unverified_rewards: HashMap<String, u64>,
+    pub problem_statement: String,
+    pub min_players: usize,
+    pub submission_fee: u64,
+    pub solution_price: u64,
+    // Buyers of each revealed solution, keyed by solution hash
+    pub purchases: HashMap<String, Vec<String>>,
+    pub total_submissions: u64,
+    pub expired_submissions: Vec<ExpiredSubmission>,
+    pub vote_quorum: u32,
+    // (approvals, rejections) of pending submissions, keyed by solution hash
+    pub votes: HashMap<String, (u32, u32)>,
+    pub voters: HashMap<String, HashSet<String>>,
+    pub max_stored_solutions: usize,
+    pub paused: bool,
+    pub asset_id: String,
+    // Rewards credited by the latest event, replaced on every event
+    pub settlements: Vec<Settlement>,
+    // Submissions dropped by the latest event, replaced on every event
+    pub rejections: Vec<Rejection>,
+    // Vouchers issued by the latest event, replaced on every event
+    pub vouchers: Vec<Voucher>,
+    // Solutions accepted by the latest event, replaced on every event
+    pub accepted: Vec<AcceptedSolution>,
+    pub voucher_nonce: u64,
+    // Discussion of each accepted solution, keyed by solution hash
+    pub comments: HashMap<String, Vec<String>>,
+    pub max_reward: u64,
+    pub first_solver_bonus: u64,
+    pub first_solver: Option<String>,
+    // When each accepted solution was accepted, keyed by solution hash
+    pub accepted_at: HashMap<String, u64>,
+    pub duplicate_penalty: u64,
+    // Hashes of accepted solutions, oldest first
+    pub accepted_order: Vec<String>,
+    pub started: bool,
+    pub evaluators: Vec<String>,
+    pub max_pending: usize,
+    // Endorsement counts and endorsers of accepted solutions, keyed by solution hash
+    pub endorsements: HashMap<String, u32>,
+    pub endorsers: HashMap<String, HashSet<String>>,
+    // Full hashes of accepted solutions, when the transactor provided them
+    pub full_hashes: HashMap<String, String>,
+    // Prior work cited by accepted solutions, keyed by solution hash
+    pub references: HashMap<String, Vec<String>>,
+    pub padding: Padding,
+    // Addresses of the servers currently serving the game
+    pub servers: Vec<String>,
+    // Real owners of the anonymous solutions, keyed by solution hash. Not exposed through
+    // any getter, solutions list ANONYMOUS_OWNER instead.
+    anonymous_owners: HashMap<String, String>,
+    pub recycle_fees: bool,
+    // Recycled fees, paid out on top of the reward for the next accepted solution
+    pub prize_pool: u64,
+    // Reward credited to the solver of each accepted solution, keyed by solution hash
+    pub rewards: HashMap<String, u64>,
+    // Player balances at the end of each round, oldest first
+    pub round_history: Vec<HashMap<String, u64>>,
+    // Solutions submitted this soon after the game starts don't win the first solver bonus
+    pub start_grace_ms: u64,
+    // When the game last started
+    pub started_at: u64,
+    pub hints: Vec<String>,
+    pub hint_price: u64,
+    // Hints bought by the latest event, replaced on every event
+    pub hints_delivered: Vec<HintDelivery>,
+    // Number of solutions accepted since the game started, including pruned ones
+    pub total_accepted: u64,
+    // Players allowed to request each restricted solution, keyed by solution hash
+    pub allowed_viewers: HashMap<String, Vec<String>>,
+    pub vote_weight_threshold: u64,
+    // Balance-weighted (approvals, rejections) of pending submissions, keyed by solution hash.
+    // Each vote weighs the voter's balance at the time of voting.
+    pub vote_weights: HashMap<String, (u64, u64)>,
+    // (solution hash, challenger) of the open challenge
+    pub dispute: Option<(String, String)>,
+    // Hashes of the solutions accepted since the last round ended, the ones open to a challenge
+    pub round_accepted: HashSet<String>,
+    // Hashes of the solutions dropped by an upheld challenge, treated as duplicates from then on
+    pub disqualified: HashSet<String>,
+    // The game account's player limit, zero means unbounded
+    pub max_players: usize,
+    pub evict_when_full: bool,
+    pub evaluator_fee: u64,
+    pub forfeit_kicked_balance: bool,
+    pub case_insensitive_addrs: bool,
+    // Addresses of the players who were removed from the game. Their accepted solutions stay.
+    pub former_players: HashSet<String>,
 }
 
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 #[derive(BorshSerialize, BorshDeserialize)]
-pub struct ScienceGameCheckpoint {}
+pub struct ScienceGameCheckpoint {
+    pub version: u16,
+}
+
+impl ScienceGameCheckpoint {
+    // Decode a checkpoint, refusing versions this build doesn't know how to migrate
+    pub fn restore(bytes: &[u8]) -> Result<Self, HandleError> {
+        let checkpoint = Self::try_from_slice(bytes).map_err(|_| HandleError::SerializationError)?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(ScienceError::UnsupportedCheckpointVersion.into());
+        }
+        Ok(checkpoint)
+    }
+}
+
+// The outcome an Evaluate event would have, see [[ScienceGame::preview_evaluate]].
+#[derive(Debug, PartialEq, Eq)]
+pub enum EvaluateOutcome {
+    Duplicate,
+    WouldCredit(u64),
+    UnknownPlayer,
+    StorageFull,
+}
 
 impl ScienceGame {
 
@@ -109,107 +1019,1731 @@ impl ScienceGame {
         sender: String,
         event: GameEvent,
     ) -> Result<(), HandleError> {
+        let gated = matches!(
+            event,
+            GameEvent::Submit { .. } | GameEvent::SubmitCompressed(_) | GameEvent::Evaluate(_) | GameEvent::EvaluateBatch(_)
+        );
+        if self.paused && gated {
+            return Err(ScienceError::GamePaused.into());
+        }
+        if self.stage == GameStage::Disputed && gated {
+            return Err(ScienceError::SolutionDisputed.into());
+        }
+
         match event {
-            GameEvent::Submit(encrypt_solution) => {
-                let mut found = false;
-                for player in &self.players {
-                    if sender.eq(&player.addr) {
-                        found = true;
-                    }           
-                }  
-                if !found {
-                    return Err(HandleError::InvalidPlayer);
+            GameEvent::Submit { payload, signature, nonce } => {
+                self.submit(effect.timestamp(), sender, payload, &signature, nonce)?;
+            }
+
+            // Compressed payloads are inflated on receipt, so the queue only holds ciphertexts.
+            // They carry no signature, so players with a signing key must use Submit.
+            GameEvent::SubmitCompressed(compressed_solution) => {
+                let encrypt_solution = decompress_payload(&compressed_solution)?;
+                self.submit(effect.timestamp(), sender, encrypt_solution, &[], None)?;
+            }
+
+            // Players who joined without a verify_key may register a first key unsigned. Replacing
+            // a key takes a signature by the current one, see [[signing_key_rotation]].
+            GameEvent::RegisterSigningKey { key, signature } => {
+                VerifyKey::from_bytes(&key).map_err(|_| HandleError::from(ScienceError::InvalidSigningKey))?;
+                let player = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                if !player.signing_key.is_empty() {
+                    let signed = signing_key_rotation(&player.addr, player.key_rotations, &key);
+                    if !verify_signature(&player.signing_key, &signed, &signature) {
+                        return Err(ScienceError::InvalidSignature.into());
+                    }
                 }
-                self.tmp_solutions.push_back(encrypt_solution);
-                self.stage = GameStage::Submitted;
+                player.signing_key = key;
+                player.key_rotations = player.key_rotations.saturating_add(1);
             }
 
             GameEvent::Evaluate(message) => {
-                self.tmp_solutions.pop_front();
-                
-                let encrypt_solution = message.content;
-                if self.encrypt_solutions.contains_key(&encrypt_solution) {
-                    self.stage = GameStage::Waiting;
-                    println!("Submitted solution already exists");
-                    return Ok(());
+                self.ensure_evaluator(&sender)?;
+                self.evaluate(effect, &sender, message)?;
+            }
+
+            // Every sender is checked up front, so an unknown one fails the whole batch
+            // before anything is credited. Duplicates are skipped as for a single Evaluate.
+            GameEvent::EvaluateBatch(messages) => {
+                self.ensure_evaluator(&sender)?;
+                for message in messages.iter() {
+                    if !self.players.iter().any(|p| same_addr(&p.addr, &message.sender, self.case_insensitive_addrs)) {
+                        return Err(ScienceError::PlayerNotFound.into());
+                    }
+                    message.check_claims()?;
                 }
-                let mut player = find_player(& mut self.players, message.sender).unwrap();
-                
-                player.balance += self.coin_assigned;
-                self.encrypt_solutions.insert(encrypt_solution, player.addr.clone());
-            
-                effect.action_timeout(player.addr.clone(), ACTION_TIMEOUT);
+                for message in messages.into_iter() {
+                    self.evaluate(effect, &sender, message)?;
+                }
+            }
 
-                // Sync solutions to all players
-                for player in self.players.iter_mut() {
-                    player.local_encrypt_solutions = self.encrypt_solutions.clone()                  
-                } 
+            // Refresh the sender's local cache from the authoritative solutions
+            GameEvent::Resync => {
+                let player = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                player.local_encrypt_solutions = self.encrypt_solutions.clone();
             }
-        }
 
-        Ok(())
-    }
-}
+            // Only the owner can reveal, and the plaintext must hash to the accepted solution.
+            // Solutions whose short hash collided are stored under their full hash.
+            GameEvent::RevealSolution { hash, plaintext } => {
+                let owner = self.real_owner(&hash).ok_or(ScienceError::UnknownSolution)?;
+                if !same_addr(&owner, &sender, self.case_insensitive_addrs) {
+                    return Err(ScienceError::NotSolutionOwner.into());
+                }
+                if hash_solution(&plaintext) != hash && full_hash_solution(&plaintext) != hash {
+                    return Err(ScienceError::RevealMismatch.into());
+                }
+                self.revealed.insert(hash, plaintext);
+            }
 
+            // Credited balances are kept, only the stored solutions are dropped
+            GameEvent::Prune(hashes) => {
+                self.ensure_transactor(&sender)?;
+                for hash in hashes.iter() {
+                    self.remove_solution(hash);
+                }
+                self.broadcast_solutions();
+            }
 
-impl GameHandler for ScienceGame {
+            // Both rewards end up with whoever found the solution first, the one accepted
+            // earliest. Seeded solutions count as earlier than any accepted one.
+            GameEvent::MergeSolutions { keep, remove } => {
+                self.ensure_transactor(&sender)?;
+                if keep == remove {
+                    return Err(ScienceError::SameSolution.into());
+                }
+                let keep_owner = self.real_owner(&keep).ok_or(ScienceError::UnknownSolution)?;
+                let remove_owner = self.real_owner(&remove).ok_or(ScienceError::UnknownSolution)?;
+                let position = |hash: &str| self.accepted_order.iter().position(|h| h == hash).map_or(0, |i| i + 1);
+                let (first_hash, first_owner, later_hash, later_owner) = if position(&remove) < position(&keep) {
+                    (remove.clone(), remove_owner, keep.clone(), keep_owner)
+                } else {
+                    (keep.clone(), keep_owner, remove.clone(), remove_owner)
+                };
 
-    type Checkpoint = ScienceGameCheckpoint;
+                // The later owner may have spent part of the reward already. Owners who aren't
+                // players, such as those of seeded solutions or who left, have no balance to move.
+                let later_reward = self.rewards.get(&later_hash).copied().unwrap_or(0);
+                let later_index = self.player_index(&later_owner);
+                let first_index = self.player_index(&first_owner).filter(|first| Some(*first) != later_index);
+                if let (Some(later_index), Some(first_index)) = (later_index, first_index) {
+                    let moved = later_reward.min(self.players[later_index].balance);
+                    let first_balance = self.players[first_index]
+                        .balance
+                        .checked_add(moved)
+                        .ok_or(ScienceError::RewardOverflow)?;
+                    self.players[later_index].balance -= moved;
+                    self.players[first_index].balance = first_balance;
+                }
 
-    fn init_state(_effect: &mut Effect, init_account: InitAccount) -> Result<Self, HandleError> {
-        let AccountData {
-            coin_assigned,
-            public_key, 
-            encrypt_solutions,
-        } = init_account.data()?;
-        let players: Vec<Player> = init_account
-            .players
-            .into_iter()
-            .map(|p| Player {
-                addr: p.addr,
-                balance: p.balance,
-                local_encrypt_solutions: encrypt_solutions.clone(),
-            })
-            .collect();
-        Ok(Self {
-            players,
-            coin_assigned,
-            public_key,
-            encrypt_solutions,
-            tmp_solutions: VecDeque::new(),
-            stage: GameStage::Waiting,
-        })
-    }
+                let reward = self.rewards.get(&first_hash).copied().unwrap_or(0).saturating_add(later_reward);
+                let held = self.unverified_rewards.get(&keep).copied().unwrap_or(0)
+                    .saturating_add(self.unverified_rewards.get(&remove).copied().unwrap_or(0));
+                let listed_owner = self.encrypt_solutions.get(first_hash.as_str()).cloned().unwrap_or_default();
+                let anonymous_owner = self.anonymous_owners.get(&first_hash).cloned();
+                // The kept solution takes over the place of the earlier one
+                if first_hash == remove {
+                    match self.accepted_at.get(&remove).copied() {
+                        Some(at) => self.accepted_at.insert(keep.clone(), at),
+                        None => self.accepted_at.remove(&keep),
+                    };
+                    self.accepted_order.retain(|hash| *hash != keep);
+                    if let Some(slot) = self.accepted_order.iter_mut().find(|hash| **hash == remove) {
+                        *slot = keep.clone();
+                    }
+                    if self.seeded_hashes.contains(&remove) {
+                        self.seeded_hashes.insert(keep.clone());
+                    }
+                }
+                self.remove_solution(&remove);
+                self.encrypt_solutions.insert(keep.clone().into(), listed_owner);
+                match anonymous_owner {
+                    Some(owner) => self.anonymous_owners.insert(keep.clone(), owner),
+                    None => self.anonymous_owners.remove(&keep),
+                };
+                if reward > 0 {
+                    self.rewards.insert(keep.clone(), reward);
+                }
+                if held > 0 {
+                    self.unverified_rewards.insert(keep, held);
+                }
+                self.broadcast_solutions();
+            }
 
-    fn handle_event(&mut self, effect: &mut Effect, event: Event) -> Result<(), HandleError> {
-        match event {
-            // Custom events are the events we defined for this game particularly
-            // See [[GameEvent]].
-            Event::Custom { sender, raw } => {
-                let event = GameEvent::try_parse(&raw)?;
-                self.custom_handle_event(effect, sender, event)?;
+            // A submission whose hash is already accepted or pending is dropped right away,
+            // sparing the transactor a wasted evaluation.
+            GameEvent::MarkPendingHash { index, hash } => {
+                self.ensure_transactor(&sender)?;
+                if index >= self.tmp_solutions.len() {
+                    return Err(ScienceError::UnknownPendingSubmission.into());
+                }
+                let pending_duplicate = self
+                    .tmp_solutions
+                    .iter()
+                    .enumerate()
+                    .any(|(i, p)| i != index && p.hash.as_deref() == Some(hash.as_str()));
+                let reserved = self.encrypt_solutions.contains_key(hash.as_str())
+                    || self.disqualified.contains(&hash);
+                if pending_duplicate || reserved {
+                    if let Some(pending) = self.tmp_solutions.remove(index) {
+                        self.reject(pending.sender.clone(), ScienceError::DuplicateSolution);
+                        self.forfeit(Some(pending));
+                    }
+                    if self.tmp_solutions.is_empty() {
+                        self.stage = GameStage::Waiting;
+                    }
+                } else {
+                    self.tmp_solutions[index].hash = Some(hash);
+                }
             }
 
-            // Sync solutions to any new joint players.
-            Event::Sync { new_players, .. } => {
-                for p in new_players.into_iter() {
-                    self.players.push(Player {
-                        addr: p.addr,
-                        balance: p.balance,
-                        local_encrypt_solutions: self.encrypt_solutions.clone(),
-                    });
+            GameEvent::Heartbeat => {
+                let now = effect.timestamp();
+                let player = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                player.last_seen = now;
+            }
+
+            // The first player other than the owner to verify a revealed solution
+            // receives the reward held back at evaluation
+            GameEvent::Verify(hash) => {
+                if !self.revealed.contains_key(&hash) {
+                    return Err(ScienceError::NotRevealed.into());
+                }
+                let owner = self.real_owner(&hash).ok_or(ScienceError::UnknownSolution)?;
+                if same_addr(&owner, &sender, self.case_insensitive_addrs) {
+                    return Err(ScienceError::SelfVerification.into());
                 }
+                let reward = *self
+                    .unverified_rewards
+                    .get(&hash)
+                    .ok_or(ScienceError::AlreadyVerified)?;
+                self.credit(&sender, reward)?;
+                self.unverified_rewards.remove(&hash);
             }
 
+            // Clients read the key from the state, so new submissions use it right away
+            GameEvent::UpdatePublicKey(public_key) => {
+                self.ensure_transactor(&sender)?;
+                RsaPublicKey::from_public_key_pem(&public_key)
+                    .map_err(|_| HandleError::from(ScienceError::InvalidPublicKey))?;
+                self.public_key = public_key;
+            }
 
-            _ => (),
-        }
+            // The buyer pays solution_price to the owner, once per solution
+            GameEvent::RequestSolution(hash) => {
+                if !self.revealed.contains_key(&hash) {
+                    return Err(ScienceError::NotRevealed.into());
+                }
+                let owner = self.real_owner(&hash).ok_or(ScienceError::UnknownSolution)?;
+                let owner_index = self.player_index(&owner).ok_or(ScienceError::PlayerNotFound)?;
+                let buyer_index = self.player_index(&sender).ok_or(ScienceError::PlayerNotFound)?;
+                if owner_index == buyer_index {
+                    return Err(ScienceError::SelfPurchase.into());
+                }
+                let buyer = self.players[buyer_index].addr.clone();
+                if !self.may_view(&hash, &buyer) {
+                    return Err(ScienceError::NotAllowedViewer.into());
+                }
+                let buyers = self.purchases.get(&hash);
+                if buyers.is_some_and(|buyers| buyers.contains(&buyer)) {
+                    return Err(ScienceError::AlreadyPurchased.into());
+                }
+                let buyer_balance = self.players[buyer_index]
+                    .balance
+                    .checked_sub(self.solution_price)
+                    .ok_or(ScienceError::InsufficientBalance)?;
+                let owner_balance = self.players[owner_index]
+                    .balance
+                    .checked_add(self.solution_price)
+                    .ok_or(ScienceError::RewardOverflow)?;
+                self.players[buyer_index].balance = buyer_balance;
+                self.players[owner_index].balance = owner_balance;
+                self.purchases.entry(hash).or_default().push(buyer);
+            }
 
-        Ok(())
-    }
+            // Once a quorum of players approves a pending submission its submitter is
+            // credited, and once a quorum rejects it the submission is dropped. With a
+            // vote_weight_threshold, the quorum is reached by balance rather than head count.
+            GameEvent::Vote { hash, approve } => {
+                if self.vote_quorum == 0 && self.vote_weight_threshold == 0 {
+                    return Err(ScienceError::VotingDisabled.into());
+                }
+                let voter = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                let (voter, weight) = (voter.addr.clone(), voter.balance);
+                let index = self
+                    .tmp_solutions
+                    .iter()
+                    .position(|p| p.hash.as_deref() == Some(hash.as_str()))
+                    .ok_or(ScienceError::UnknownPendingSubmission)?;
+                if same_addr(&self.tmp_solutions[index].sender, &voter, self.case_insensitive_addrs) {
+                    return Err(ScienceError::SelfVote.into());
+                }
+                if !self.voters.entry(hash.clone()).or_default().insert(voter) {
+                    return Err(ScienceError::AlreadyVoted.into());
+                }
+                let tally = self.votes.entry(hash.clone()).or_insert((0, 0));
+                let weights = self.vote_weights.entry(hash.clone()).or_insert((0, 0));
+                if approve {
+                    tally.0 += 1;
+                    weights.0 = weights.0.saturating_add(weight);
+                } else {
+                    tally.1 += 1;
+                    weights.1 = weights.1.saturating_add(weight);
+                }
+                let (approved, rejected) = if self.vote_weight_threshold > 0 {
+                    (weights.0 >= self.vote_weight_threshold, weights.1 >= self.vote_weight_threshold)
+                } else {
+                    (tally.0 >= self.vote_quorum, tally.1 >= self.vote_quorum)
+                };
+                if !approved && !rejected {
+                    return Ok(());
+                }
 
-    fn into_checkpoint(self) -> HandleResult<ScienceGameCheckpoint> {
-        Ok(ScienceGameCheckpoint {})
+                self.votes.remove(&hash);
+                self.vote_weights.remove(&hash);
+                self.voters.remove(&hash);
+                let pending = self.tmp_solutions.remove(index);
+                if approved
+                    && !self.encrypt_solutions.contains_key(hash.as_str())
+                    && !self.storage_full()
+                {
+                    let solver = pending.as_ref().map(|p| p.sender.clone()).unwrap_or_default();
+                    let owner = self.accept_solution(effect.timestamp(), hash, &solver, MIN_DIFFICULTY, false, pending)?;
+                    self.schedule_timeout(effect, owner);
+                } else {
+                    self.forfeit(pending);
+                    if self.tmp_solutions.is_empty() {
+                        self.stage = GameStage::Waiting;
+                    }
+                }
+            }
+
+            GameEvent::Flag(addr) => {
+                self.ensure_transactor(&sender)?;
+                find_player(& mut self.players, addr, self.case_insensitive_addrs)?.flagged = true;
+            }
+
+            GameEvent::Unflag(addr) => {
+                self.ensure_transactor(&sender)?;
+                find_player(& mut self.players, addr, self.case_insensitive_addrs)?.flagged = false;
+            }
+
+            GameEvent::Kick(addr) => {
+                self.ensure_transactor(&sender)?;
+                self.kick(&addr)?;
+            }
+
+            // Only once everything queued is evaluated, so the queue stays empty while disputed
+            GameEvent::Challenge(hash) => {
+                let challenger = find_player(& mut self.players, sender, self.case_insensitive_addrs)?.addr.clone();
+                if self.stage != GameStage::Evaluated || !self.round_accepted.contains(&hash) {
+                    return Err(ScienceError::NotChallengeable.into());
+                }
+                let owner = self.real_owner(&hash).ok_or(ScienceError::UnknownSolution)?;
+                if same_addr(&owner, &challenger, self.case_insensitive_addrs) {
+                    return Err(ScienceError::SelfChallenge.into());
+                }
+                self.dispute = Some((hash, challenger));
+                self.stage = GameStage::Disputed;
+            }
+
+            // An upheld challenge drops the solution, while credited balances are kept as for
+            // Prune. The hash stays reserved, so the solution can't be accepted and rewarded again.
+            // Either way the round resumes and ends as scheduled after an evaluation.
+            GameEvent::ResolveChallenge { upheld } => {
+                self.ensure_transactor(&sender)?;
+                let (hash, _) = self.dispute.take().ok_or(ScienceError::NoDispute)?;
+                if upheld {
+                    self.remove_solution(&hash);
+                    self.disqualified.insert(hash);
+                    self.broadcast_solutions();
+                }
+                if self.stage == GameStage::Disputed {
+                    self.stage = GameStage::Evaluated;
+                    self.schedule_round_end(effect);
+                }
+            }
+
+            GameEvent::Pause => {
+                self.ensure_transactor(&sender)?;
+                self.paused = true;
+            }
+
+            GameEvent::Resume => {
+                self.ensure_transactor(&sender)?;
+                self.paused = false;
+            }
+
+            GameEvent::Comment { hash, text } => {
+                find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                if !self.encrypt_solutions.contains_key(hash.as_str()) {
+                    return Err(ScienceError::UnknownSolution.into());
+                }
+                if text.trim().is_empty() {
+                    return Err(ScienceError::EmptySubmission.into());
+                }
+                if text.chars().count() > MAX_COMMENT_LEN {
+                    return Err(ScienceError::CommentTooLong.into());
+                }
+                let comments = self.comments.entry(hash).or_default();
+                if comments.len() >= MAX_COMMENTS_PER_SOLUTION {
+                    return Err(ScienceError::TooManyComments.into());
+                }
+                comments.push(text);
+            }
+
+            GameEvent::SetAllowedViewers { hash, viewers } => {
+                let owner = self.real_owner(&hash).ok_or(ScienceError::UnknownSolution)?;
+                if !same_addr(&owner, &sender, self.case_insensitive_addrs) {
+                    return Err(ScienceError::NotSolutionOwner.into());
+                }
+                match viewers {
+                    Some(viewers) => {
+                        self.allowed_viewers.insert(hash, viewers.iter().map(|v| normalize_addr(v)).collect());
+                    }
+                    None => {
+                        self.allowed_viewers.remove(&hash);
+                    }
+                }
+            }
+
+            GameEvent::TransferOwnership { hash, to } => {
+                let owner = self.real_owner(&hash).ok_or(ScienceError::UnknownSolution)?;
+                if !same_addr(&owner, &sender, self.case_insensitive_addrs) {
+                    return Err(ScienceError::NotSolutionOwner.into());
+                }
+                let to = find_player(& mut self.players, to, self.case_insensitive_addrs)?.addr.clone();
+                // An anonymous solution stays listed as anonymous under its new owner
+                if self.anonymous_owners.contains_key(&hash) {
+                    self.anonymous_owners.insert(hash, to);
+                } else {
+                    self.encrypt_solutions.insert(hash.into(), to);
+                }
+                self.broadcast_solutions();
+            }
+
+            GameEvent::AddEvaluator(addr) => {
+                self.ensure_evaluator(&sender)?;
+                if !self.evaluators.iter().any(|e| same_addr(e, &addr, self.case_insensitive_addrs)) {
+                    self.evaluators.push(normalize_addr(&addr));
+                }
+            }
+
+            GameEvent::RemoveEvaluator(addr) => {
+                self.ensure_evaluator(&sender)?;
+                self.evaluators.retain(|e| !same_addr(e, &addr, self.case_insensitive_addrs));
+            }
+
+            GameEvent::Reset => {
+                self.ensure_transactor(&sender)?;
+                self.reset();
+            }
+
+            // Solutions to the old problem are dropped and pending submissions refunded, while
+            // balances already earned are kept
+            GameEvent::SetProblemStatement(statement) => {
+                self.ensure_transactor(&sender)?;
+                if statement.trim().is_empty() {
+                    return Err(ScienceError::EmptyProblemStatement.into());
+                }
+                while let Some(pending) = self.tmp_solutions.pop_front() {
+                    self.refund(Some(pending))?;
+                }
+                self.clear_solutions();
+                self.problem_statement = statement;
+                self.broadcast_solutions();
+            }
+
+            // Nicknames are compared case-insensitively, so two players can't look alike
+            GameEvent::SetNickname(nickname) => {
+                let nickname = nickname.trim().to_string();
+                if nickname.is_empty() || nickname.chars().count() > MAX_NICKNAME_LEN {
+                    return Err(ScienceError::InvalidNickname.into());
+                }
+                let index = self.player_index(&sender).ok_or(ScienceError::PlayerNotFound)?;
+                let taken = self.players.iter().enumerate().any(|(i, p)| {
+                    i != index
+                        && p.nickname.as_ref().is_some_and(|n| n.to_lowercase() == nickname.to_lowercase())
+                });
+                if taken {
+                    return Err(ScienceError::NicknameTaken.into());
+                }
+                self.players[index].nickname = Some(nickname);
+            }
+
+            GameEvent::Endorse(hash) => {
+                let endorser = find_player(& mut self.players, sender, self.case_insensitive_addrs)?.addr.clone();
+                let owner = self.real_owner(&hash).ok_or(ScienceError::UnknownSolution)?;
+                if same_addr(&owner, &endorser, self.case_insensitive_addrs) {
+                    return Err(ScienceError::SelfEndorsement.into());
+                }
+                if !self.endorsers.entry(hash.clone()).or_default().insert(endorser) {
+                    return Err(ScienceError::AlreadyEndorsed.into());
+                }
+                *self.endorsements.entry(hash).or_default() += 1;
+            }
+
+            // Refunded submissions are dropped from expired_submissions, so a fee is only
+            // ever refunded once
+            GameEvent::RequestRefund => {
+                let index = self.player_index(&sender).ok_or(ScienceError::PlayerNotFound)?;
+                let addr = self.players[index].addr.clone();
+                let (refunded, kept): (Vec<ExpiredSubmission>, Vec<ExpiredSubmission>) =
+                    std::mem::take(&mut self.expired_submissions)
+                        .into_iter()
+                        .partition(|e| same_addr(&e.sender, &addr, self.case_insensitive_addrs));
+                self.expired_submissions = kept;
+                if refunded.is_empty() {
+                    if self.tmp_solutions.iter().any(|p| same_addr(&p.sender, &addr, self.case_insensitive_addrs)) {
+                        return Err(ScienceError::StillPending.into());
+                    }
+                    return Err(ScienceError::NoExpiredSubmission.into());
+                }
+                let player = &mut self.players[index];
+                for expired in refunded.iter() {
+                    player.balance = player
+                        .balance
+                        .checked_add(expired.fee)
+                        .ok_or(ScienceError::RewardOverflow)?;
+                }
+            }
+
+            GameEvent::Redeem(amount) => {
+                if amount == 0 {
+                    return Err(ScienceError::InvalidAmount.into());
+                }
+                let player = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                player.balance = player
+                    .balance
+                    .checked_sub(amount)
+                    .ok_or(ScienceError::InsufficientBalance)?;
+                let addr = player.addr.clone();
+                let id = voucher_id(&addr, self.voucher_nonce);
+                self.voucher_nonce += 1;
+                self.vouchers.push(Voucher { addr, amount, id });
+            }
+
+            GameEvent::Donate(amount) => {
+                if amount == 0 {
+                    return Err(ScienceError::InvalidAmount.into());
+                }
+                let prize_pool = self.prize_pool.checked_add(amount).ok_or(ScienceError::RewardOverflow)?;
+                let player = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                player.balance = player
+                    .balance
+                    .checked_sub(amount)
+                    .ok_or(ScienceError::InsufficientBalance)?;
+                self.prize_pool = prize_pool;
+            }
+
+            // Every player gets the hints in the same order, paying for each one
+            GameEvent::RequestHint => {
+                let hint_price = self.hint_price;
+                let player = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+                let hint = self.hints.get(player.hints_used).ok_or(ScienceError::NoHintLeft)?.clone();
+                player.balance = player
+                    .balance
+                    .checked_sub(hint_price)
+                    .ok_or(ScienceError::InsufficientBalance)?;
+                player.hints_used += 1;
+                let addr = player.addr.clone();
+                self.hints_delivered.push(HintDelivery { addr, hint });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Only the transactor may send privileged events. Until the transactor address is
+    // known, nobody may.
+    fn ensure_transactor(&self, sender: &str) -> Result<(), HandleError> {
+        let authorized = self
+            .transactor_addr
+            .as_deref()
+            .is_some_and(|transactor_addr| same_addr(transactor_addr, sender, self.case_insensitive_addrs));
+        if !authorized {
+            return Err(ScienceError::NotTransactor.into());
+        }
+        Ok(())
+    }
+
+    // Evaluations may come from the transactor or any of the configured evaluators
+    fn ensure_evaluator(&self, sender: &str) -> Result<(), HandleError> {
+        if self.evaluators.iter().any(|e| same_addr(e, sender, self.case_insensitive_addrs)) {
+            return Ok(());
+        }
+        self.ensure_transactor(sender)
+    }
+
+    fn player_index(&self, addr: &str) -> Option<usize> {
+        self.players.iter().position(|p| same_addr(&p.addr, addr, self.case_insensitive_addrs))
+    }
+
+    // Remove a player along with their pending submissions, whose fees are forfeited. Their
+    // balance goes to the prize pool with forfeit_kicked_balance, and is otherwise settled to
+    // them. Their accepted solutions stay, so they can't be accepted and rewarded again.
+    fn kick(&mut self, addr: &str) -> Result<(), HandleError> {
+        let index = self.player_index(addr).ok_or(ScienceError::PlayerNotFound)?;
+        let balance = self.players[index].balance;
+        let (player, dropped) = self.remove_player(index);
+        for p in dropped.into_iter() {
+            self.forfeit(Some(p));
+        }
+        if self.forfeit_kicked_balance {
+            self.prize_pool = self.prize_pool.checked_add(balance).ok_or(ScienceError::RewardOverflow)?;
+        } else {
+            self.settle_departure(player.addr, balance);
+        }
+        Ok(())
+    }
+
+    // Make room for a new player. The evicted player did nothing wrong, so their pending fees
+    // are refunded and settled to them along with their balance.
+    fn evict(&mut self, index: usize) -> Result<(), HandleError> {
+        let amount = self
+            .tmp_solutions
+            .iter()
+            .filter(|p| same_addr(&p.sender, &self.players[index].addr, self.case_insensitive_addrs))
+            .try_fold(self.players[index].balance, |amount, p| amount.checked_add(p.fee))
+            .ok_or(ScienceError::RewardOverflow)?;
+        let (player, _) = self.remove_player(index);
+        self.settle_departure(player.addr, amount);
+        Ok(())
+    }
+
+    // Take a player out of the game along with their pending submissions, which are returned
+    // for the caller to deal with their fees. Their accepted solutions stay.
+    fn remove_player(&mut self, index: usize) -> (Player, VecDeque<PendingSubmission>) {
+        let player = self.players.remove(index);
+        let (dropped, pending): (VecDeque<PendingSubmission>, VecDeque<PendingSubmission>) =
+            std::mem::take(&mut self.tmp_solutions)
+                .into_iter()
+                .partition(|p| same_addr(&p.sender, &player.addr, self.case_insensitive_addrs));
+        self.tmp_solutions = pending;
+        if self.tmp_solutions.is_empty() && self.stage == GameStage::Submitted {
+            self.stage = GameStage::Waiting;
+        }
+        self.former_players.insert(player.addr.clone());
+        (player, dropped)
+    }
+
+    // Pay out what a departing player is owed, for the transactor to settle on chain
+    fn settle_departure(&mut self, addr: String, amount: u64) {
+        if amount > 0 {
+            self.settlements.push(Settlement {
+                addr,
+                amount,
+                asset_id: self.asset_id.clone(),
+            });
+        }
+    }
+
+    // Drop the pending submissions older than ACTION_TIMEOUT, e.g. while the transactor is offline,
+    // letting their submitters know through a rejection
+    fn expire_pending(&mut self, now: u64) {
+        let (expired, pending): (VecDeque<PendingSubmission>, VecDeque<PendingSubmission>) =
+            std::mem::take(&mut self.tmp_solutions)
+                .into_iter()
+                .partition(|p| now.saturating_sub(p.submitted_at) > ACTION_TIMEOUT);
+        self.tmp_solutions = pending;
+        if expired.is_empty() {
+            return;
+        }
+        for p in expired.into_iter() {
+            self.reject(p.sender.clone(), ScienceError::SubmissionExpired);
+            self.expired_submissions.push(ExpiredSubmission {
+                sender: p.sender,
+                fee: p.fee,
+                submitted_at: p.submitted_at,
+            });
+        }
+        if self.tmp_solutions.is_empty() {
+            self.stage = GameStage::Waiting;
+        }
+    }
+
+    // Credit a player, keeping total_distributed in step. Nothing is mutated on overflow.
+    // Returns the player's canonical address.
+    fn credit(&mut self, addr: &str, amount: u64) -> Result<String, HandleError> {
+        let total_distributed = self
+            .total_distributed
+            .checked_add(amount)
+            .ok_or(ScienceError::RewardOverflow)?;
+        let player = find_player(& mut self.players, addr.to_string(), self.case_insensitive_addrs)?;
+        player.balance = player
+            .balance
+            .checked_add(amount)
+            .ok_or(ScienceError::RewardOverflow)?;
+        let addr = player.addr.clone();
+        self.total_distributed = total_distributed;
+        self.settlements.push(Settlement {
+            addr: addr.clone(),
+            amount,
+            asset_id: self.asset_id.clone(),
+        });
+        Ok(addr)
+    }
+
+    // coin_assigned scaled by the solution's difficulty, capped at max_reward if set
+    fn scaled_reward(&self, difficulty: u8) -> u64 {
+        let reward = self.coin_assigned.saturating_mul(difficulty as u64);
+        if self.max_reward > 0 {
+            reward.min(self.max_reward)
+        } else {
+            reward
+        }
+    }
+
+    // The part of a reward held back for the first verifier, never more than the reward
+    fn verifier_reward(&self, reward: u64) -> u64 {
+        (reward as u128 * self.verifier_share.min(100) as u128 / 100) as u64
+    }
+
+    // The part of a reward credited to the solver on acceptance
+    fn solver_reward(&self, reward: u64) -> u64 {
+        reward - self.verifier_reward(reward)
+    }
+
+    // Return the fee paid for a pending submission to its sender
+    fn refund(&mut self, pending: Option<PendingSubmission>) -> Result<(), HandleError> {
+        if let Some(pending) = pending.filter(|p| p.fee > 0) {
+            let player = find_player(& mut self.players, pending.sender, self.case_insensitive_addrs)?;
+            player.balance = player
+                .balance
+                .checked_add(pending.fee)
+                .ok_or(ScienceError::RewardOverflow)?;
+        }
+        Ok(())
+    }
+
+    // Keep the fee of a dropped submission, either burning it or recycling it into the prize pool
+    fn forfeit(&mut self, pending: Option<PendingSubmission>) {
+        if let Some(pending) = pending.filter(|_| self.recycle_fees) {
+            self.prize_pool = self.prize_pool.saturating_add(pending.fee);
+        }
+    }
+
+    // Whether max_stored_solutions is reached. New solutions are then rejected rather than
+    // evicting old ones, since an evicted solution could be submitted and rewarded again.
+    // Pruning frees up space.
+    pub fn storage_full(&self) -> bool {
+        self.max_stored_solutions > 0 && self.encrypt_solutions.len() >= self.max_stored_solutions
+    }
+
+    // Record why a submission was dropped, for the submitter to pick up
+    fn reject(&mut self, addr: String, reason: ScienceError) {
+        println!("Submitted solution rejected: {}", reason);
+        self.rejections.push(Rejection {
+            addr,
+            code: reason.code(),
+        });
+    }
+
+    // Whether `addr` may request a solution, always the case unless its owner restricted it
+    fn may_view(&self, hash: &str, addr: &str) -> bool {
+        self.allowed_viewers
+            .get(hash)
+            .is_none_or(|viewers| viewers.iter().any(|v| same_addr(v, addr, self.case_insensitive_addrs)))
+    }
+
+    // The player who found a solution, also for anonymous ones
+    fn real_owner(&self, hash: &str) -> Option<String> {
+        self.anonymous_owners
+            .get(hash)
+            .or_else(|| self.encrypt_solutions.get(hash))
+            .cloned()
+    }
+
+    // Forget an accepted solution along with everything attached to it
+    fn remove_solution(&mut self, hash: &str) {
+        self.encrypt_solutions.remove(hash);
+        self.revealed.remove(hash);
+        self.unverified_rewards.remove(hash);
+        self.purchases.remove(hash);
+        self.comments.remove(hash);
+        self.accepted_at.remove(hash);
+        self.endorsements.remove(hash);
+        self.endorsers.remove(hash);
+        self.full_hashes.remove(hash);
+        self.references.remove(hash);
+        self.anonymous_owners.remove(hash);
+        self.rewards.remove(hash);
+        self.allowed_viewers.remove(hash);
+        self.accepted_order.retain(|accepted| accepted != hash);
+        self.round_accepted.remove(hash);
+    }
+
+    // Sync solutions to all players
+    fn broadcast_solutions(&mut self) {
+        for player in self.players.iter_mut() {
+            player.local_encrypt_solutions = self.encrypt_solutions.clone()
+        }
+    }
+
+    // Credit the solver with the reward for `difficulty` and record the solution as accepted at
+    // `now`, refunding the fee paid for its pending submission if any. Returns the solver's
+    // canonical address.
+    fn accept_solution(
+        &mut self,
+        now: u64,
+        hash: String,
+        solver: &str,
+        difficulty: u8,
+        anonymous: bool,
+        pending: Option<PendingSubmission>,
+    ) -> Result<String, HandleError> {
+        let scaled_reward = self.scaled_reward(difficulty);
+        let held_reward = self.verifier_reward(scaled_reward);
+        let reward = self.solver_reward(scaled_reward);
+        let bonus_eligible = self.bonus_eligible(pending.as_ref().map_or(now, |p| p.submitted_at));
+        let owner = self.credit(solver, reward)?;
+        let listed_owner = if anonymous {
+            self.anonymous_owners.insert(hash.clone(), owner.clone());
+            ANONYMOUS_OWNER.to_string()
+        } else {
+            owner.clone()
+        };
+        if bonus_eligible {
+            if self.first_solver_bonus > 0 {
+                self.credit(&owner, self.first_solver_bonus)?;
+            }
+            self.first_solver = Some(listed_owner.clone());
+        }
+        if self.prize_pool > 0 {
+            self.credit(&owner, self.prize_pool)?;
+            self.prize_pool = 0;
+        }
+        if held_reward > 0 {
+            self.unverified_rewards.insert(hash.clone(), held_reward);
+        }
+        self.accepted_at.insert(hash.clone(), now);
+        self.rewards.insert(hash.clone(), reward);
+        self.accepted_order.push(hash.clone());
+        self.round_accepted.insert(hash.clone());
+        self.total_accepted = self.total_accepted.saturating_add(1);
+        self.accepted.push(AcceptedSolution {
+            hash: hash.clone(),
+            solver: listed_owner.clone(),
+            reward,
+        });
+        self.encrypt_solutions.insert(hash.into(), listed_owner);
+        self.refund(pending)?;
+        if self.tmp_solutions.is_empty() {
+            self.stage = GameStage::Evaluated;
+        }
+
+        self.broadcast_solutions();
+        Ok(owner)
+    }
+
+    // Whether a solution submitted at `submitted_at` would be the first one, thus winning the
+    // first solver bonus. Solutions submitted within the start grace period never are, so
+    // the bonus can't be front-run by submitting right at the start.
+    fn bonus_eligible(&self, submitted_at: u64) -> bool {
+        let in_grace = self.started && submitted_at < self.started_at.saturating_add(self.start_grace_ms);
+        self.first_solver.is_none() && !in_grace
+    }
+
+    // Check that accepting a solution from `solver` evaluated by `evaluator` can't fail half way
+    // through, i.e. that crediting the reward, any first solver bonus, the evaluator fee and the
+    // rest of the prize pool, and refunding the pending fee, all fit
+    fn check_acceptance(
+        &self,
+        now: u64,
+        evaluator: &str,
+        solver: &str,
+        difficulty: u8,
+        pending: Option<&PendingSubmission>,
+    ) -> Result<(), HandleError> {
+        let bonus_eligible = self.bonus_eligible(pending.map_or(now, |p| p.submitted_at));
+        let bonus = if bonus_eligible { self.first_solver_bonus } else { 0 };
+        let evaluator_fee = self.evaluator_fee();
+        let credited = self
+            .solver_reward(self.scaled_reward(difficulty))
+            .checked_add(bonus)
+            .and_then(|credited| credited.checked_add(self.prize_pool - evaluator_fee))
+            .ok_or(ScienceError::RewardOverflow)?;
+        let solver_index = self.player_index(solver).ok_or(ScienceError::PlayerNotFound)?;
+        // Added to each player's balance, by player index
+        let mut additions = vec![(solver_index, credited)];
+        let mut distributed = credited;
+        if let Some(index) = self.player_index(evaluator).filter(|_| evaluator_fee > 0) {
+            additions.push((index, evaluator_fee));
+            distributed = distributed.checked_add(evaluator_fee).ok_or(ScienceError::RewardOverflow)?;
+        }
+        if let Some(pending) = pending.filter(|p| p.fee > 0) {
+            let index = self.player_index(&pending.sender).ok_or(ScienceError::PlayerNotFound)?;
+            additions.push((index, pending.fee));
+        }
+        self.total_distributed
+            .checked_add(distributed)
+            .ok_or(ScienceError::RewardOverflow)?;
+        for (index, _) in additions.iter() {
+            additions
+                .iter()
+                .filter(|(other, _)| other == index)
+                .try_fold(self.players[*index].balance, |balance, (_, amount)| balance.checked_add(*amount))
+                .ok_or(ScienceError::RewardOverflow)?;
+        }
+        Ok(())
+    }
+
+    // The part of the prize pool paid to whoever evaluates the next accepted solution
+    fn evaluator_fee(&self) -> u64 {
+        self.evaluator_fee.min(self.prize_pool)
+    }
+
+    // The key to store a solution under. A short hash that is already accepted with a different
+    // full hash is a collision, so the solution is kept under its full hash instead. Without
+    // both full hashes there is no telling, and the solution counts as a duplicate.
+    fn solution_key(&self, hash: String, full_hash: &str) -> String {
+        match self.full_hashes.get(&hash) {
+            Some(accepted) if !full_hash.is_empty() && accepted != full_hash => full_hash.to_string(),
+            _ => hash,
+        }
+    }
+
+    // Evaluate the submission at the front of the queue, using the hash computed by the transactor
+    fn evaluate(&mut self, effect: &mut Effect, evaluator: &str, message: Message) -> Result<(), HandleError> {
+        // The sender embedded in the ciphertext must be the player who submitted it,
+        // otherwise anyone could claim a solution on behalf of another player
+        if let Some(front) = self.tmp_solutions.front() {
+            if !same_addr(&front.sender, &message.sender, self.case_insensitive_addrs) {
+                return Err(ScienceError::SenderMismatch.into());
+            }
+        }
+        message.check_claims()?;
+        if message.content.is_empty() {
+            return Err(ScienceError::EmptySubmission.into());
+        }
+        let encrypt_solution = self.solution_key(message.content, &message.full_hash);
+        let outcome = self.preview_evaluate(&encrypt_solution, &message.sender, message.difficulty);
+        match outcome {
+            EvaluateOutcome::UnknownPlayer => return Err(ScienceError::PlayerNotFound.into()),
+            EvaluateOutcome::WouldCredit(_) => {
+                let pending = self.tmp_solutions.front();
+                self.check_acceptance(effect.timestamp(), evaluator, &message.sender, message.difficulty, pending)?
+            }
+            _ => (),
+        }
+
+        // Everything is validated, so nothing below fails half way through
+        let pending = self.tmp_solutions.pop_front();
+        match outcome {
+            EvaluateOutcome::Duplicate => {
+                if self.tmp_solutions.is_empty() {
+                    self.stage = GameStage::Waiting;
+                }
+                if let Some(index) = self.player_index(&message.sender) {
+                    let player = &mut self.players[index];
+                    player.balance = player.balance.saturating_sub(self.duplicate_penalty);
+                }
+                self.forfeit(pending);
+                self.reject(message.sender, ScienceError::DuplicateSolution);
+                return Ok(());
+            }
+            // Not the submitter's fault, so the fee is returned
+            EvaluateOutcome::StorageFull => {
+                self.refund(pending)?;
+                if self.tmp_solutions.is_empty() {
+                    self.stage = GameStage::Waiting;
+                }
+                self.reject(message.sender, ScienceError::StorageFull);
+                return Ok(());
+            }
+            EvaluateOutcome::WouldCredit(_) | EvaluateOutcome::UnknownPlayer => (),
+        }
+        // Drawn before the solver collects the rest of the pool
+        let evaluator_fee = self.evaluator_fee();
+        if evaluator_fee > 0 {
+            self.prize_pool -= evaluator_fee;
+            self.pay_evaluator(evaluator, evaluator_fee)?;
+        }
+        let owner = self.accept_solution(
+            effect.timestamp(),
+            encrypt_solution.clone(),
+            &message.sender,
+            message.difficulty,
+            message.anonymous,
+            pending,
+        )?;
+        if !message.references.is_empty() {
+            self.references.insert(encrypt_solution.clone(), message.references);
+        }
+        if !message.full_hash.is_empty() {
+            self.full_hashes.insert(encrypt_solution, message.full_hash);
+        }
+        self.schedule_timeout(effect, owner);
+        Ok(())
+    }
+
+    // Evaluators who joined the game are credited like players, others, such as the transactor,
+    // are only settled on chain
+    fn pay_evaluator(&mut self, evaluator: &str, amount: u64) -> Result<(), HandleError> {
+        if self.player_index(evaluator).is_some() {
+            self.credit(evaluator, amount)?;
+        } else {
+            self.settlements.push(Settlement {
+                addr: normalize_addr(evaluator),
+                amount,
+                asset_id: self.asset_id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    // After an acceptance, give the owner ACTION_TIMEOUT to act, unless that emptied the queue
+    // and the round is ending. Only one of the two timeouts is set, an action timeout would
+    // take precedence and the round would never end.
+    fn schedule_timeout(&self, effect: &mut Effect, owner: String) {
+        if self.stage == GameStage::Evaluated {
+            self.schedule_round_end(effect);
+        } else {
+            effect.action_timeout(owner, ACTION_TIMEOUT);
+        }
+    }
+
+    // Once everything queued is evaluated, the round ends after NEXT_GAME_TIMEOUT
+    fn schedule_round_end(&self, effect: &mut Effect) {
+        if self.stage == GameStage::Evaluated {
+            effect.wait_timeout(NEXT_GAME_TIMEOUT);
+        }
+    }
+
+    // Record the standings of the round just finished and start waiting for the next one
+    fn end_round(&mut self) {
+        let balances = self.players.iter().map(|p| (p.addr.clone(), p.balance)).collect();
+        self.round_history.push(balances);
+        if self.round_history.len() > MAX_ROUND_HISTORY {
+            self.round_history.remove(0);
+        }
+        self.round_accepted.clear();
+        self.stage = GameStage::Waiting;
+    }
+
+    // Queue an encrypted solution for the transactor to evaluate
+    fn submit(
+        &mut self,
+        now: u64,
+        sender: String,
+        encrypt_solution: Vec<u8>,
+        signature: &[u8],
+        nonce: Option<u64>,
+    ) -> Result<(), HandleError> {
+        let player_count = self.players.len();
+        let storage_full = self.storage_full();
+        let player = find_player(& mut self.players, sender, self.case_insensitive_addrs)?;
+        if player.flagged {
+            return Err(ScienceError::PlayerFlagged.into());
+        }
+        // The nonce is signed along with the payload, so a replay can't strip or change it
+        if !player.signing_key.is_empty() {
+            let nonce = nonce.ok_or(ScienceError::MissingNonce)?;
+            if !verify_signature(&player.signing_key, &signed_submission(&encrypt_solution, nonce), signature) {
+                return Err(ScienceError::InvalidSignature.into());
+            }
+        }
+        if nonce.is_some_and(|nonce| nonce <= player.last_nonce) {
+            return Ok(());
+        }
+        if player_count < self.min_players {
+            return Err(ScienceError::NotEnoughPlayers.into());
+        }
+        if encrypt_solution.is_empty() {
+            return Err(ScienceError::EmptySubmission.into());
+        }
+        if storage_full {
+            return Err(ScienceError::StorageFull.into());
+        }
+        // Backpressure for when the transactor can't keep up
+        if self.max_pending > 0 && self.tmp_solutions.len() >= self.max_pending {
+            return Err(ScienceError::PendingQueueFull.into());
+        }
+        // Broke players can't spam the queue, even when submissions are free
+        if player.balance == 0 {
+            return Err(ScienceError::InsufficientBalance.into());
+        }
+        player.balance = player
+            .balance
+            .checked_sub(self.submission_fee)
+            .ok_or(ScienceError::InsufficientBalance)?;
+        if let Some(nonce) = nonce {
+            player.last_nonce = nonce;
+        }
+        let pending = PendingSubmission {
+            sender: player.addr.clone(),
+            payload: encrypt_solution,
+            hash: None,
+            fee: self.submission_fee,
+            submitted_at: now,
+        };
+        // Keep the queue ordered by (submitted_at, sender), so the evaluation order, and thus
+        // who wins between identical submissions, doesn't depend on event arrival order.
+        let index = self
+            .tmp_solutions
+            .iter()
+            .position(|p| p.order_key() > pending.order_key())
+            .unwrap_or(self.tmp_solutions.len());
+        self.tmp_solutions.insert(index, pending);
+        self.total_submissions = self.total_submissions.saturating_add(1);
+        self.stage = GameStage::Submitted;
+        Ok(())
+    }
+
+    // Preview what evaluating a solution hash submitted by `sender` at the claimed difficulty
+    // would do, without mutating state
+    pub fn preview_evaluate(&self, hash: &str, sender: &str, difficulty: u8) -> EvaluateOutcome {
+        if self.encrypt_solutions.contains_key(hash) || self.disqualified.contains(hash) {
+            return EvaluateOutcome::Duplicate;
+        }
+        if !self.players.iter().any(|p| same_addr(&p.addr, sender, self.case_insensitive_addrs)) {
+            return EvaluateOutcome::UnknownPlayer;
+        }
+        if self.storage_full() {
+            return EvaluateOutcome::StorageFull;
+        }
+        EvaluateOutcome::WouldCredit(self.solver_reward(self.scaled_reward(difficulty)))
+    }
+
+    // Verify the state is self-consistent:
+    // - solutions are owned by joined or former players, except for seeded ones
+    // - pending submissions come from joined players
+    // - revealed, held back and purchased entries refer to accepted solutions
+    // - the players' balances add up without overflowing
+    // - the stage is Submitted exactly when submissions are pending
+    pub fn check_invariants(&self) -> Result<(), HandleError> {
+        let is_player = |addr: &str| self.players.iter().any(|p| same_addr(&p.addr, addr, self.case_insensitive_addrs));
+        let is_owner = |addr: &str| is_player(addr) || self.former_players.iter().any(|f| same_addr(f, addr, self.case_insensitive_addrs));
+        let owners_known = self
+            .encrypt_solutions
+            .iter()
+            .all(|(hash, owner)| {
+                self.seeded_hashes.contains(hash.as_str())
+                    || is_owner(owner)
+                    || self.anonymous_owners.get(hash.as_str()).is_some_and(|real| is_owner(real))
+            });
+        let senders_known = self.tmp_solutions.iter().all(|p| is_player(&p.sender));
+        let references_accepted = self.revealed.keys().all(|hash| self.encrypt_solutions.contains_key(hash.as_str()))
+            && self.unverified_rewards.keys().all(|hash| self.encrypt_solutions.contains_key(hash.as_str()))
+            && self.purchases.keys().all(|hash| self.revealed.contains_key(hash));
+        let balances_fit = self
+            .players
+            .iter()
+            .try_fold(0u64, |sum, p| sum.checked_add(p.balance))
+            .is_some();
+        let stage_consistent = self.tmp_solutions.is_empty() != (self.stage == GameStage::Submitted);
+
+        if owners_known && senders_known && references_accepted && balances_fit && stage_consistent {
+            Ok(())
+        } else {
+            Err(ScienceError::BrokenInvariant.into())
+        }
+    }
+
+    // (sum of player balances, total_distributed) for auditing solvency. The sum is clamped
+    // at u64::MAX should it overflow, which check_invariants reports as a broken state.
+    pub fn accounting_summary(&self) -> (u64, u64) {
+        let balances = self
+            .players
+            .iter()
+            .try_fold(0u64, |sum, p| sum.checked_add(p.balance))
+            .unwrap_or(u64::MAX);
+        (balances, self.total_distributed)
+    }
+
+    // Number of submissions queued since the game started, whether accepted later or not.
+    // A rejected Submit event is rolled back with the rest of the state, so it is never counted.
+    pub fn total_submissions(&self) -> u64 {
+        self.total_submissions
+    }
+
+    // Share of the queued submissions that got accepted, 0.0 before any submission. Solutions
+    // accepted by vote or evaluated without being queued count too, so this may exceed 1.0.
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.total_submissions == 0 {
+            return 0.0;
+        }
+        self.total_accepted as f64 / self.total_submissions as f64
+    }
+
+    // Total coins credited to solvers since the game started
+    pub fn total_distributed(&self) -> u64 {
+        self.total_distributed
+    }
+
+    // Addresses of the current players, in join order
+    pub fn player_addrs(&self) -> Vec<String> {
+        self.players.iter().map(|p| p.addr.clone()).collect()
+    }
+
+    // A game with the given players and reward and everything else at its default, for tests
+    // exercising individual methods without going through init_state
+    pub fn new_for_test(players: Vec<Player>, coin_assigned: u64) -> Self {
+        Self {
+            players,
+            coin_assigned,
+            ..Default::default()
+        }
+    }
+
+    // Whether a server able to evaluate submissions is serving the game. Front ends can warn
+    // that submissions won't be processed otherwise.
+    pub fn has_evaluator(&self) -> bool {
+        self.servers.iter().any(|server| {
+            self.transactor_addr.as_deref().is_some_and(|t| same_addr(t, server, self.case_insensitive_addrs))
+                || self.evaluators.iter().any(|e| same_addr(e, server, self.case_insensitive_addrs))
+        })
+    }
+
+    // Clear the solutions, pending submissions and balances for a new game instance. Players
+    // stay joined, and the configuration, transactor and voucher nonce are kept.
+    pub fn reset(&mut self) {
+        self.clear_solutions();
+        self.tmp_solutions.clear();
+        for player in self.players.iter_mut() {
+            player.balance = 0;
+        }
+        self.total_distributed = 0;
+        self.total_submissions = 0;
+        self.total_accepted = 0;
+        self.expired_submissions.clear();
+        self.prize_pool = 0;
+        self.round_history.clear();
+        self.started = false;
+    }
+
+    // Drop everything tied to the current problem: solutions and their metadata. Balances
+    // and pending submissions are left to the caller.
+    fn clear_solutions(&mut self) {
+        self.encrypt_solutions.clear();
+        self.stage = GameStage::Waiting;
+        for player in self.players.iter_mut() {
+            player.local_encrypt_solutions.clear();
+        }
+        self.revealed.clear();
+        self.seeded_hashes.clear();
+        self.unverified_rewards.clear();
+        self.purchases.clear();
+        self.votes.clear();
+        self.vote_weights.clear();
+        self.voters.clear();
+        self.comments.clear();
+        self.first_solver = None;
+        self.accepted_at.clear();
+        self.accepted_order.clear();
+        self.endorsements.clear();
+        self.endorsers.clear();
+        self.full_hashes.clear();
+        self.references.clear();
+        self.anonymous_owners.clear();
+        self.rewards.clear();
+        self.allowed_viewers.clear();
+        self.dispute = None;
+        self.round_accepted.clear();
+        self.disqualified.clear();
+    }
+
+    // (address, balance) of every player, richest first. Ties are broken by address so the
+    // order doesn't depend on join order.
+    pub fn leaderboard(&self) -> Vec<(String, u64)> {
+        let mut board: Vec<(String, u64)> = self.players.iter().map(|p| (p.addr.clone(), p.balance)).collect();
+        board.sort_by(|(a_addr, a_balance), (b_addr, b_balance)| b_balance.cmp(a_balance).then(a_addr.cmp(b_addr)));
+        board
+    }
+
+    // Index of the player at the bottom of the leaderboard, the one evicted to make room when full
+    fn lowest_balance_player(&self) -> Option<usize> {
+        let (addr, _) = self.leaderboard().pop()?;
+        self.player_index(&addr)
+    }
+
+    // 1-based position of a player in the leaderboard
+    pub fn rank_of(&self, addr: &str) -> Option<usize> {
+        self.leaderboard()
+            .iter()
+            .position(|(player, _)| same_addr(player, addr, self.case_insensitive_addrs))
+            .map(|index| index + 1)
+    }
+
+    // Addresses of the players who sent a heartbeat within `window` milliseconds before `now`
+    pub fn players_seen_within(&self, now: u64, window: u64) -> Vec<String> {
+        self.players
+            .iter()
+            .filter(|p| p.last_seen > 0 && now.saturating_sub(p.last_seen) <= window)
+            .map(|p| p.addr.clone())
+            .collect()
+    }
+
+    // Hashes of the pending submissions the transactor has decrypted so far, in queue order
+    pub fn pending_hashes(&self) -> Vec<String> {
+        self.tmp_solutions
+            .iter()
+            .filter_map(|p| p.hash.clone())
+            .collect()
+    }
+
+    // Submitters of the pending submissions older than `max_age` milliseconds at `now`, in queue
+    // order. A player with several stale submissions is listed once per submission.
+    pub fn stale_pending(&self, now: u64, max_age: u64) -> Vec<String> {
+        self.tmp_solutions
+            .iter()
+            .filter(|p| now.saturating_sub(p.submitted_at) > max_age)
+            .map(|p| p.sender.clone())
+            .collect()
+    }
+
+    // Number of pending submissions queued by each player. Players with nothing queued are
+    // left out.
+    pub fn pending_per_player(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for pending in self.tmp_solutions.iter() {
+            *counts.entry(pending.sender.clone()).or_default() += 1;
+        }
+        counts
+    }
+
+    // Decrypt the pending submission at `index` in the queue with the game's padding, as the
+    // transactor does before evaluating it
+    pub(crate) fn decrypt_pending(&self, index: usize, private_key: &RsaPrivateKey) -> Result<Message, HandleError> {
+        let pending = self.tmp_solutions.get(index).ok_or(ScienceError::UnknownPendingSubmission)?;
+        Ok(decrypt_message(&pending.payload, private_key, self.padding)?)
+    }
+
+    // Submitter of the pending submission at `index` in the queue, 0 being the next one to be
+    // evaluated. Known without decrypting the payload.
+    pub fn pending_submitter(&self, index: usize) -> Option<&str> {
+        self.tmp_solutions.get(index).map(|p| p.sender.as_str())
+    }
+
+    // The problem this game collects solutions for
+    pub fn problem_statement(&self) -> &str {
+        &self.problem_statement
+    }
+
+    // Number of distinct players owning an accepted solution. Anonymous solutions count for
+    // their real owner, without revealing who that is.
+    pub fn unique_solvers(&self) -> usize {
+        self.encrypt_solutions
+            .iter()
+            .map(|(hash, owner)| self.anonymous_owners.get(hash.as_str()).unwrap_or(owner))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    pub fn public_view(&self, for_addr: &str) -> PublicView {
+        let mut solutions: Vec<String> = self.encrypt_solutions.keys().map(|hash| hash.to_string()).collect();
+        solutions.sort();
+        PublicView {
+            addr: normalize_addr(for_addr),
+            balance: self.player_index(for_addr).map(|index| self.players[index].balance),
+            solutions,
+            stage: self.stage.clone(),
+        }
+    }
+
+    pub fn config(&self) -> GameConfig {
+        GameConfig {
+            coin_assigned: self.coin_assigned,
+            problem_statement: self.problem_statement.clone(),
+            verifier_share: self.verifier_share,
+            min_players: self.min_players,
+            submission_fee: self.submission_fee,
+            solution_price: self.solution_price,
+            vote_quorum: self.vote_quorum,
+            max_stored_solutions: self.max_stored_solutions,
+            max_pending: self.max_pending,
+            max_reward: self.max_reward,
+            first_solver_bonus: self.first_solver_bonus,
+            duplicate_penalty: self.duplicate_penalty,
+            asset_id: self.asset_id.clone(),
+            padding: self.padding.name().to_string(),
+            recycle_fees: self.recycle_fees,
+            start_grace_ms: self.start_grace_ms,
+            hint_price: self.hint_price,
+            vote_weight_threshold: self.vote_weight_threshold,
+            max_players: self.max_players,
+            evict_when_full: self.evict_when_full,
+            evaluator_fee: self.evaluator_fee,
+            forfeit_kicked_balance: self.forfeit_kicked_balance,
+            case_insensitive_addrs: self.case_insensitive_addrs,
+            action_timeout: ACTION_TIMEOUT,
+            next_game_timeout: NEXT_GAME_TIMEOUT,
+        }
+    }
+
+    // Hashes of the solutions accepted at or after `ts`, oldest first. Seeded solutions were
+    // never accepted and are not included.
+    pub fn solutions_since(&self, ts: u64) -> Vec<String> {
+        let mut accepted: Vec<(u64, &String)> = self
+            .accepted_at
+            .iter()
+            .filter(|(_, at)| **at >= ts)
+            .map(|(hash, at)| (*at, hash))
+            .collect();
+        accepted.sort();
+        accepted.into_iter().map(|(_, hash)| hash.clone()).collect()
+    }
+
+    // The last `n` accepted (hash, owner) pairs, oldest first, for an activity feed
+    pub fn recent_solutions(&self, n: usize) -> Vec<(String, String)> {
+        let start = self.accepted_order.len().saturating_sub(n);
+        self.accepted_order[start..]
+            .iter()
+            .filter_map(|hash| {
+                let owner = self.encrypt_solutions.get(hash.as_str())?;
+                Some((hash.clone(), owner.clone()))
+            })
+            .collect()
+    }
+
+    // Players whose local cache no longer matches the accepted solutions, for debugging sync
+    pub fn diverged_players(&self) -> Vec<String> {
+        self.players
+            .iter()
+            .filter(|p| p.local_encrypt_solutions != self.encrypt_solutions)
+            .map(|p| p.addr.clone())
+            .collect()
+    }
+
+    // Whether every player's local cache matches the accepted solutions
+    pub fn all_caches_synced(&self) -> bool {
+        self.players.iter().all(|p| p.local_encrypt_solutions == self.encrypt_solutions)
+    }
+
+    // Number of players who endorsed a solution
+    pub fn endorsements(&self, hash: &str) -> u32 {
+        self.endorsements.get(hash).copied().unwrap_or(0)
+    }
+
+    // Prior work cited by an accepted solution
+    pub fn references(&self, hash: &str) -> &[String] {
+        self.references.get(hash).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // Comments attached to a solution, oldest first
+    pub fn comments(&self, hash: &str) -> &[String] {
+        self.comments.get(hash).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // Number of solutions seeded from the account data at init
+    pub fn seeded_count(&self) -> usize {
+        self.seeded_hashes.len()
+    }
+
+    // Size in bytes of the Borsh-serialized state, to monitor on-chain storage growth
+    pub fn serialized_size(&self) -> Result<usize, HandleError> {
+        borsh::to_vec(self)
+            .map(|bytes| bytes.len())
+            .map_err(|_| HandleError::SerializationError)
+    }
+
+    // Iterate over the accepted (hash, owner) pairs without cloning the map
+    pub fn iter_solutions(&self) -> impl Iterator<Item = (&SolutionHash, &String)> {
+        self.encrypt_solutions.iter()
+    }
+
+    // List the solution hashes credited to the given player, sorted for a stable output
+    pub fn solutions_by(&self, addr: &str) -> Vec<String> {
+        let mut hashes: Vec<String> = self
+            .iter_solutions()
+            .filter(|(_, owner)| same_addr(owner, addr, self.case_insensitive_addrs))
+            .map(|(hash, _)| hash.to_string())
+            .collect();
+        hashes.sort();
+        hashes
+    }
+
+    // List the solution hashes starting with `prefix`, sorted for a stable output
+    pub fn solutions_with_prefix(&self, prefix: &str) -> Vec<String> {
+        let mut hashes: Vec<String> = self
+            .iter_solutions()
+            .filter(|(hash, _)| hash.as_str().starts_with(prefix))
+            .map(|(hash, _)| hash.to_string())
+            .collect();
+        hashes.sort();
+        hashes
+    }
+
+    // The accepted solutions as `hash,owner,balance` CSV with a header, sorted by hash. The
+    // balance is the owner's current one, 0 for owners who aren't playing or are anonymous.
+    pub fn to_csv(&self) -> String {
+        let mut rows: Vec<(&SolutionHash, &String)> = self.iter_solutions().collect();
+        rows.sort();
+        let mut csv = String::from("hash,owner,balance\n");
+        for (hash, owner) in rows.into_iter() {
+            let balance = self.player_index(owner).map_or(0, |index| self.players[index].balance);
+            csv.push_str(&format!("{},{},{}\n", hash, owner, balance));
+        }
+        csv
+    }
+
+    // List the solution hashes whose plaintext hasn't been revealed yet, sorted for a stable output
+    pub fn unrevealed_solutions(&self) -> Vec<String> {
+        let mut hashes: Vec<String> = self
+            .iter_solutions()
+            .filter(|(hash, _)| !self.revealed.contains_key(hash.as_str()))
+            .map(|(hash, _)| hash.to_string())
+            .collect();
+        hashes.sort();
+        hashes
+    }
+}
+
+
+impl GameHandler for ScienceGame {
+
+    type Checkpoint = ScienceGameCheckpoint;
+
+    fn init_state(_effect: &mut Effect, init_account: InitAccount) -> Result<Self, HandleError> {
+        let AccountData {
+            coin_assigned,
+            public_key, 
+            encrypt_solutions,
+            verifier_share,
+            problem_statement,
+            min_players,
+            submission_fee,
+            solution_price,
+            vote_quorum,
+            max_stored_solutions,
+            asset_id,
+            max_reward,
+            first_solver_bonus,
+            duplicate_penalty,
+            evaluators,
+            max_pending,
+            padding,
+            recycle_fees,
+            start_grace_ms,
+            hints,
+            hint_price,
+            vote_weight_threshold,
+            evict_when_full,
+            evaluator_fee,
+            forfeit_kicked_balance,
+            case_insensitive_addrs,
+        } = init_account.data()?;
+        // A zero reward would credit nothing and only waste transactor work
+        if verifier_share > 100 || coin_assigned == 0 {
+            return Err(ScienceError::InvalidConfig.into());
+        }
+        if problem_statement.trim().is_empty() {
+            return Err(ScienceError::EmptyProblemStatement.into());
+        }
+        let padding = Padding::parse(&padding)?;
+        let encrypt_solutions = normalize_seeded_solutions(encrypt_solutions)?;
+        // A player at ANONYMOUS_OWNER would pass for the owner of every anonymous solution
+        if init_account.players.iter().any(|p| same_addr(&p.addr, ANONYMOUS_OWNER, case_insensitive_addrs)) {
+            return Err(ScienceError::ReservedAddress.into());
+        }
+        let players: Vec<Player> = init_account
+            .players
+            .into_iter()
+            .map(|p| {
+                Ok(Player {
+                    addr: normalize_addr(&p.addr),
+                    balance: p.balance,
+                    local_encrypt_solutions: encrypt_solutions.clone(),
+                    last_seen: 0,
+                    flagged: false,
+                    nickname: None,
+                    signing_key: parse_verify_key(&p.verify_key)?,
+                    hints_used: 0,
+                    key_rotations: 0,
+                    last_nonce: 0,
+                })
+            })
+            .collect::<Result<_, HandleError>>()?;
+        Ok(Self {
+            players,
+            coin_assigned,
+            public_key,
+            seeded_hashes: encrypt_solutions.keys().map(|hash| hash.to_string()).collect(),
+            // The first server to join the game serves as its transactor
+            transactor_addr: init_account.servers.first().map(|s| normalize_addr(&s.addr)),
+            encrypt_solutions,
+            tmp_solutions: VecDeque::new(),
+            stage: GameStage::Waiting,
+            total_distributed: 0,
+            revealed: HashMap::new(),
+            verifier_share,
+            unverified_rewards: HashMap::new(),
+            problem_statement,
+            min_players,
+            submission_fee,
+            solution_price,
+            purchases: HashMap::new(),
+            total_submissions: 0,
+            expired_submissions: Vec::new(),
+            vote_quorum,
+            votes: HashMap::new(),
+            voters: HashMap::new(),
+            max_stored_solutions,
+            paused: false,
+            asset_id,
+            settlements: Vec::new(),
+            rejections: Vec::new(),
+            vouchers: Vec::new(),
+            accepted: Vec::new(),
+            voucher_nonce: 0,
+            comments: HashMap::new(),
+            max_reward,
+            first_solver_bonus,
+            first_solver: None,
+            accepted_at: HashMap::new(),
+            duplicate_penalty,
+            accepted_order: Vec::new(),
+            started: false,
+            evaluators: evaluators.iter().map(|addr| normalize_addr(addr)).collect(),
+            max_pending,
+            endorsements: HashMap::new(),
+            endorsers: HashMap::new(),
+            full_hashes: HashMap::new(),
+            references: HashMap::new(),
+            padding,
+            servers: init_account.servers.iter().map(|s| normalize_addr(&s.addr)).collect(),
+            anonymous_owners: HashMap::new(),
+            recycle_fees,
+            prize_pool: 0,
+            rewards: HashMap::new(),
+            round_history: Vec::new(),
+            start_grace_ms,
+            started_at: 0,
+            hints,
+            hint_price,
+            hints_delivered: Vec::new(),
+            total_accepted: 0,
+            allowed_viewers: HashMap::new(),
+            vote_weight_threshold,
+            vote_weights: HashMap::new(),
+            dispute: None,
+            round_accepted: HashSet::new(),
+            disqualified: HashSet::new(),
+            max_players: init_account.max_players as usize,
+            evict_when_full,
+            evaluator_fee,
+            forfeit_kicked_balance,
+            case_insensitive_addrs,
+            former_players: HashSet::new(),
+        })
+    }
+
+    fn handle_event(&mut self, effect: &mut Effect, event: Event) -> Result<(), HandleError> {
+        self.settlements.clear();
+        self.rejections.clear();
+        self.vouchers.clear();
+        self.accepted.clear();
+        self.hints_delivered.clear();
+        // Any event, including WaitingTimeout, gives a chance to drop stale submissions
+        self.expire_pending(effect.timestamp());
+
+        match event {
+            // Custom events are the events we defined for this game particularly
+            // See [[GameEvent]].
+            Event::Custom { sender, raw } => {
+                let event = GameEvent::try_parse(&raw)
+                    .map_err(|_| HandleError::from(ScienceError::MalformedEvent))?;
+                self.custom_handle_event(effect, sender, event)?;
+            }
+
+            // Sync solutions to any new joint players. Players already in the game are
+            // skipped, so replayed syncs neither duplicate them nor touch their caches.
+            // New players are added by access version, then address, so every node ends up
+            // with the same player order whatever order they arrived in.
+            Event::Sync { mut new_players, new_servers, transactor_addr, .. } => {
+                new_players.sort_by(|a, b| {
+                    a.access_version
+                        .cmp(&b.access_version)
+                        .then_with(|| normalize_addr(&a.addr).cmp(&normalize_addr(&b.addr)))
+                });
+                if new_players.iter().any(|p| same_addr(&p.addr, ANONYMOUS_OWNER, self.case_insensitive_addrs)) {
+                    return Err(ScienceError::ReservedAddress.into());
+                }
+                let signing_keys = new_players
+                    .iter()
+                    .map(|p| parse_verify_key(&p.verify_key))
+                    .collect::<Result<Vec<_>, HandleError>>()?;
+                self.transactor_addr = Some(normalize_addr(&transactor_addr));
+                for s in new_servers.into_iter() {
+                    if !self.servers.iter().any(|server| same_addr(server, &s.addr, self.case_insensitive_addrs)) {
+                        self.servers.push(normalize_addr(&s.addr));
+                    }
+                }
+                for (p, signing_key) in new_players.into_iter().zip(signing_keys) {
+                    if self.players.iter().any(|player| same_addr(&player.addr, &p.addr, self.case_insensitive_addrs)) {
+                        continue;
+                    }
+                    // The player already joined on chain, so they are admitted either way
+                    if self.evict_when_full && self.max_players > 0 && self.players.len() >= self.max_players {
+                        if let Some(index) = self.lowest_balance_player() {
+                            self.evict(index)?;
+                        }
+                    }
+                    self.players.push(Player {
+                        addr: normalize_addr(&p.addr),
+                        balance: p.balance,
+                        local_encrypt_solutions: self.encrypt_solutions.clone(),
+                        last_seen: 0,
+                        flagged: false,
+                        nickname: None,
+                        signing_key,
+                        hints_used: 0,
+                        key_rotations: 0,
+                        last_nonce: 0,
+                    });
+                }
+            }
+
+            // The game can only start once enough players have joined. The stage follows
+            // the queue, so a restart resumes evaluating anything still pending.
+            Event::GameStart { .. } => {
+                if self.players.len() < self.min_players {
+                    return Err(ScienceError::NotEnoughPlayers.into());
+                }
+                self.started = true;
+                self.started_at = effect.timestamp();
+                self.stage = if self.tmp_solutions.is_empty() {
+                    GameStage::Waiting
+                } else {
+                    GameStage::Submitted
+                };
+            }
+
+            // Nothing will be evaluated after a shutdown, so pending fees are refunded.
+            // Solutions and balances are kept.
+            Event::Shutdown => {
+                while let Some(pending) = self.tmp_solutions.pop_front() {
+                    self.refund(Some(pending))?;
+                }
+                self.started = false;
+                self.stage = GameStage::Waiting;
+            }
+
+            // Follow the transactor when the serving server changes
+            Event::ServerLeave { server_addr, transactor_addr } => {
+                self.transactor_addr = Some(normalize_addr(&transactor_addr));
+                self.servers.retain(|server| !same_addr(server, &server_addr, self.case_insensitive_addrs));
+            }
+
+            // The waiting timeout scheduled once a round is evaluated. Other waiting timeouts
+            // only expire pending submissions, which happens above.
+            Event::WaitingTimeout => {
+                if self.stage == GameStage::Evaluated {
+                    self.end_round();
+                }
+            }
+
+            // Ready, Leave and the action timeouts need no handling here
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn into_checkpoint(self) -> HandleResult<ScienceGameCheckpoint> {
+        Ok(ScienceGameCheckpoint {
+            version: CHECKPOINT_VERSION,
+        })
     }
 }
 
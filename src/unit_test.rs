@@ -5,13 +5,15 @@
 //! 2. Player submits a valid solution
 //! 3. Player submits an existing solution
 
-use crate::{AccountData, ScienceGame, GameEvent, GameStage, Player, Message, encrypt_message, decrypt_message};
+use crate::{ANONYMOUS_OWNER, AccountData, CryptoError, GameConfig, HintDelivery, PublicView, ScienceGame, ScienceGameCheckpoint, ScienceError, Settlement, SolutionHash, Rejection, Voucher, AcceptedSolution, EvaluateOutcome, GameEvent, GameStage, Player, Message, encrypt_message, decrypt_message, encrypt_for_pem, Padding, reencrypt, hash_solution, full_hash_solution, voucher_id, compress_payload, decompress_payload, signed_submission, signing_key_rotation};
 use race_api::prelude::*;
 use race_test::prelude::*;
 use rsa::{RsaPublicKey, RsaPrivateKey, PaddingScheme, PublicKey};
 use rsa::pkcs1::{FromRsaPublicKey, ToRsaPublicKey};
 use rsa::pkcs8::{FromPublicKey, ToPublicKey};
 use rand::rngs::OsRng;
+use ed25519_dalek::{ExpandedSecretKey, PublicKey as VerifyKey, SecretKey};
+use std::collections::HashMap;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
@@ -58,12 +60,14 @@ fn test() -> anyhow::Result<()> {
         .iter()
         .map(|(k, v)| (k.to_string(), v.to_string()))
         .collect(),
+        problem_statement: "Find a string whose hash is not known yet".to_string(),
+        ..Default::default()
     };
 
     println!("Create game account");
     let game_account = TestGameAccountBuilder::default()
         .set_transactor(&transactor)
-        .add_player(&players[0], 0)
+        .add_player(&players[0], 100)
         .with_max_players(1000)
         .with_data(account_data)
         .build();
@@ -103,7 +107,7 @@ fn test() -> anyhow::Result<()> {
     let sync_event = Event::Sync {
         new_players: vec![PlayerJoin {
             addr: players[1].get_addr().into(),
-            balance: 0,
+            balance: 100,
             position: 1,
             access_version: av,
             verify_key: "".into(),
@@ -120,10 +124,7 @@ fn test() -> anyhow::Result<()> {
         let state: &ScienceGame = handler.get_state();
         println!("State: {:?}", state.stage);
         // Check: Sync solution has been updated to all users
-        for player in &state.players {
-            assert_eq!(state.encrypt_solutions, player.local_encrypt_solutions);
-            println!("{:?} local hash solutions: {:?}", player.addr, player.local_encrypt_solutions);
-        }
+        assert!(state.all_caches_synced());
     }
 
     // Unit Test 2. Player submits a valid solution
@@ -132,22 +133,26 @@ fn test() -> anyhow::Result<()> {
     let message = Message {
         sender: players[0].get_addr(),
         content: "Solution10".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
     };
 
     // use transactor's public key to encrypt the message
     {
         let state: &ScienceGame = handler.get_state();
         let public_key = RsaPublicKey::from_public_key_pem(&state.public_key).expect("Failed to obtain public key");
-        let encrypt_solution = encrypt_message(&message, &public_key).expect("Failed to obtain public key");
+        let encrypt_solution = encrypt_message(&message, &public_key, state.padding).expect("Failed to obtain public key");
         // println!("Player 1 encrypts solution using transactor's public key: {:?}", encrypt_solution);
 
-        let event = players[0].custom_event(GameEvent::Submit(encrypt_solution));
+        let event = players[0].custom_event(GameEvent::Submit { payload: encrypt_solution, signature: Vec::new(), nonce: None });
         handler.handle_event(&mut ctx, &event)?; 
     }
     // Verify tmp solution queue is not empty
     {
         let state: &ScienceGame = handler.get_state();
-        let onchain_tmp_solutions = state.tmp_solutions.clone().pop_front().unwrap();
+        let onchain_tmp_solutions = state.tmp_solutions.clone().pop_front().unwrap().payload;
         assert!(onchain_tmp_solutions.len() > 0);
         println!("Current tmp solution queue: {:?}", onchain_tmp_solutions);
     }
@@ -161,9 +166,9 @@ fn test() -> anyhow::Result<()> {
 
         let mut tmp_solutions = state.tmp_solutions.clone();
 
-        let encrypt_solution = tmp_solutions.pop_front().unwrap();
+        let encrypt_solution = tmp_solutions.pop_front().unwrap().payload;
 
-        let decrypt_solution = decrypt_message(&encrypt_solution, &private_key).expect("decrypt_message error");
+        let decrypt_solution = decrypt_message(&encrypt_solution, &private_key, state.padding).expect("decrypt_message error");
 
         println!("decrypt_solution sender: {:?}", decrypt_solution.sender);
         println!("decrypt_solution: {:?}", decrypt_solution.content);
@@ -176,6 +181,10 @@ fn test() -> anyhow::Result<()> {
         let eval_message = Message {
             sender: decrypt_solution.sender,
             content: hash_solution.to_string(),
+            difficulty: 1,
+            full_hash: full_hash_solution(&decrypt_solution.content),
+            references: Vec::new(),
+            anonymous: false,
         };
 
         let event = transactor.custom_event(GameEvent::Evaluate(eval_message));
@@ -190,10 +199,7 @@ fn test() -> anyhow::Result<()> {
         let state: &ScienceGame = handler.get_state();
         println!("State: {:?}", state.stage);
         // Check: Sync solution has been updated to all users
-        for player in &state.players {
-            assert_eq!(state.encrypt_solutions, player.local_encrypt_solutions);
-            println!("{:?} local hash solutions: {:?}", player.addr, player.local_encrypt_solutions);
-        }
+        assert!(state.all_caches_synced());
     }
 
     // Unit Test 3. Player submits an existing solution
@@ -202,21 +208,25 @@ fn test() -> anyhow::Result<()> {
     let message = Message {
         sender: players[1].get_addr(),
         content: "Solution10".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
     };
 
     // use transactor's public key to encrypt the message
     {
         let state: &ScienceGame = handler.get_state();
         let public_key = RsaPublicKey::from_public_key_pem(&state.public_key).expect("Failed to obtain public key");
-        let encrypt_solution = encrypt_message(&message, &public_key).expect("Failed to obtain public key");
-        let event = players[0].custom_event(GameEvent::Submit(encrypt_solution));
+        let encrypt_solution = encrypt_message(&message, &public_key, state.padding).expect("Failed to obtain public key");
+        let event = players[1].custom_event(GameEvent::Submit { payload: encrypt_solution, signature: Vec::new(), nonce: None });
         handler.handle_event(&mut ctx, &event)?;
     }
 
     // Verify tmp solution queue is not empty
     {
         let state: &ScienceGame = handler.get_state();
-        let onchain_tmp_solutions = state.tmp_solutions.clone().pop_front().unwrap();
+        let onchain_tmp_solutions = state.tmp_solutions.clone().pop_front().unwrap().payload;
         assert!(onchain_tmp_solutions.len() > 0);
         println!("Current tmp solution queue: {:?}", onchain_tmp_solutions);
     }
@@ -230,9 +240,9 @@ fn test() -> anyhow::Result<()> {
 
         let mut tmp_solutions = state.tmp_solutions.clone();
 
-        let encrypt_solution = tmp_solutions.pop_front().unwrap();
+        let encrypt_solution = tmp_solutions.pop_front().unwrap().payload;
 
-        let decrypt_solution = decrypt_message(&encrypt_solution, &private_key).expect("decrypt_message error");
+        let decrypt_solution = decrypt_message(&encrypt_solution, &private_key, state.padding).expect("decrypt_message error");
 
         println!("decrypt_solution sender: {:?}", decrypt_solution.sender);
         println!("decrypt_solution: {:?}", decrypt_solution.content);
@@ -245,6 +255,10 @@ fn test() -> anyhow::Result<()> {
         let eval_message = Message {
             sender: decrypt_solution.sender,
             content: hash_solution.to_string(),
+            difficulty: 1,
+            full_hash: full_hash_solution(&decrypt_solution.content),
+            references: Vec::new(),
+            anonymous: false,
         };
 
         let event = transactor.custom_event(GameEvent::Evaluate(eval_message));
@@ -259,12 +273,2539 @@ fn test() -> anyhow::Result<()> {
         let state: &ScienceGame = handler.get_state();
         println!("State: {:?}", state.stage);
         // Check: Sync solution has been updated to all users
-        for player in &state.players {
-            assert_eq!(state.encrypt_solutions, player.local_encrypt_solutions);
-            println!("{:?} local hash solutions: {:?}", player.addr, player.local_encrypt_solutions);
-        }
+        assert!(state.all_caches_synced());
+    }
+
+
+    Ok(())
+}
+
+// Build a game state directly, bypassing the account setup, for testing individual handlers.
+fn new_game(addrs: &[&str]) -> ScienceGame {
+    let players = addrs
+        .iter()
+        .map(|addr| Player {
+            addr: addr.to_string(),
+            ..Default::default()
+        })
+        .collect();
+    ScienceGame {
+        transactor_addr: Some("transactor".to_string()),
+        ..ScienceGame::new_for_test(players, 1)
+    }
+}
+
+// Minimal valid account data for initializing a game.
+fn account_data() -> AccountData {
+    AccountData {
+        coin_assigned: 1,
+        problem_statement: "Find a novel solution".to_string(),
+        ..Default::default()
+    }
+}
+
+// Initialize a game served by a transactor, with alice joined, through the test harness.
+fn init_handler(account_data: AccountData) -> anyhow::Result<(GameContext, TestHandler<ScienceGame>)> {
+    let transactor = TestClient::transactor("transactor");
+    let alice = TestClient::player("alice");
+    let game_account = TestGameAccountBuilder::default()
+        .set_transactor(&transactor)
+        .add_player(&alice, 0)
+        .with_max_players(10)
+        .with_data(account_data)
+        .build();
+    let mut ctx = GameContext::try_new(&game_account)?;
+    let handler = TestHandler::init_state(&mut ctx, &game_account)?;
+    Ok((ctx, handler))
+}
+
+// Sync a new player into the game, as the transactor does when someone joins.
+fn join(game: &mut ScienceGame, addr: &str, access_version: u64) -> Result<(), HandleError> {
+    let sync_event = Event::Sync {
+        new_players: vec![PlayerJoin {
+            addr: addr.to_string(),
+            balance: 0,
+            position: access_version as u16,
+            access_version,
+            verify_key: "".into(),
+        }],
+        new_servers: vec![],
+        transactor_addr: "transactor".to_string(),
+        access_version,
+    };
+    game.handle_event(&mut Effect::default(), sync_event)
+}
+
+// Let the transactor accept a solution hash on behalf of a player.
+fn evaluate(game: &mut ScienceGame, sender: &str, hash: &str) -> Result<(), HandleError> {
+    let message = Message {
+        sender: sender.to_string(),
+        content: hash.to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Evaluate(message))
+}
+
+// Submit an opaque payload as the given player.
+fn submit(game: &mut ScienceGame, sender: &str, payload: &[u8]) -> Result<(), HandleError> {
+    game.custom_handle_event(&mut Effect::default(), sender.to_string(), GameEvent::Submit { payload: payload.to_vec(), signature: Vec::new(), nonce: None })
+}
+
+// Give the named players a balance, as if they had joined with a deposit, so they can submit.
+fn fund(game: &mut ScienceGame, addrs: &[&str], balance: u64) {
+    for player in game.players.iter_mut().filter(|p| addrs.contains(&p.addr.as_str())) {
+        player.balance = balance;
+    }
+}
+
+#[test]
+fn test_solutions_by() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "bob", "222")?;
+    evaluate(&mut game, "alice", "333")?;
+
+    assert_eq!(game.solutions_by("alice"), vec!["111".to_string(), "333".to_string()]);
+    assert_eq!(game.solutions_by("bob"), vec!["222".to_string()]);
+    assert!(game.solutions_by("carol").is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_structured_errors() {
+    let mut game = new_game(&["alice"]);
+
+    assert_eq!(submit(&mut game, "mallory", b"blob"), Err(ScienceError::PlayerNotFound.into()));
+    assert_eq!(submit(&mut game, "alice", b""), Err(ScienceError::EmptySubmission.into()));
+    assert_eq!(evaluate(&mut game, "mallory", "111"), Err(ScienceError::PlayerNotFound.into()));
+    assert_eq!(evaluate(&mut game, "alice", ""), Err(ScienceError::EmptySubmission.into()));
+
+    // The messages are part of the public contract with clients.
+    assert_eq!(
+        HandleError::from(ScienceError::DuplicateSolution),
+        HandleError::Custom("duplicate solution".to_string())
+    );
+}
+
+#[test]
+fn test_resync() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    evaluate(&mut game, "alice", "111")?;
+
+    // Simulate a desync on bob's side.
+    game.players[1].local_encrypt_solutions.clear();
+    assert_ne!(game.players[1].local_encrypt_solutions, game.encrypt_solutions);
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::Resync)?;
+    assert_eq!(game.players[1].local_encrypt_solutions, game.encrypt_solutions);
+    Ok(())
+}
+
+#[test]
+fn test_submit_compressed() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    fund(&mut game, &["alice"], 1);
+    let encrypt_solution = vec![7u8; 256];
+
+    let compressed = compress_payload(&encrypt_solution)?;
+    assert_eq!(decompress_payload(&compressed)?, encrypt_solution);
+
+    let event = GameEvent::SubmitCompressed(compressed);
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), event)?;
+    assert_eq!(game.tmp_solutions.front().map(|p| &p.payload), Some(&encrypt_solution));
+    assert_eq!(game.stage, GameStage::Submitted);
+
+    // Garbage that is not gzip data is rejected and nothing is queued.
+    let event = GameEvent::SubmitCompressed(b"not gzip".to_vec());
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), event);
+    assert_eq!(ret, Err(ScienceError::MalformedPayload.into()));
+    assert_eq!(game.tmp_solutions.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_find_player_normalizes_addr() -> anyhow::Result<()> {
+    let mut game = new_game(&["Alice"]);
+    fund(&mut game, &["Alice"], 1);
+
+    assert_eq!(crate::find_player(&mut game.players, "  Alice \n".to_string(), false)?.addr, "Alice");
+    // Case is only ignored on chains with case-insensitive addresses.
+    let ret = crate::find_player(&mut game.players, "alice".to_string(), false).map(|p| p.addr.clone());
+    assert_eq!(ret, Err(ScienceError::PlayerNotFound.into()));
+    assert_eq!(crate::find_player(&mut game.players, "alice".to_string(), true)?.addr, "Alice");
+
+    // A padded sender can submit, and its credited solutions are found by the canonical address.
+    submit(&mut game, " Alice ", b"blob")?;
+    assert_eq!(evaluate(&mut game, " alice", "111"), Err(ScienceError::SenderMismatch.into()));
+    game.case_insensitive_addrs = true;
+    evaluate(&mut game, " alice", "111")?;
+    assert_eq!(game.players[0].balance, 2);
+    assert_eq!(game.solutions_by("Alice"), vec!["111".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn test_preview_evaluate() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    game.coin_assigned = 5;
+
+    assert_eq!(game.preview_evaluate("111", "alice", 1), EvaluateOutcome::WouldCredit(5));
+    assert_eq!(game.preview_evaluate("111", "mallory", 1), EvaluateOutcome::UnknownPlayer);
+    // Previewing leaves the state untouched.
+    assert!(game.encrypt_solutions.is_empty());
+    assert_eq!(game.players[0].balance, 0);
+
+    evaluate(&mut game, "alice", "111")?;
+    assert_eq!(game.preview_evaluate("111", "alice", 1), EvaluateOutcome::Duplicate);
+    Ok(())
+}
+
+#[test]
+fn test_total_distributed() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 3;
+
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "bob", "222")?;
+    // Duplicates are not rewarded.
+    evaluate(&mut game, "bob", "111")?;
+    assert_eq!(game.total_distributed(), 6);
+
+    // An overflowing reward is rejected instead of wrapping around.
+    game.total_distributed = u64::MAX;
+    assert_eq!(evaluate(&mut game, "alice", "333"), Err(ScienceError::RewardOverflow.into()));
+    assert_eq!(game.players[0].balance, 3);
+    Ok(())
+}
+
+#[test]
+fn test_reveal_solution() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    let hash = hash_solution("Solution10");
+    evaluate(&mut game, "alice", &hash)?;
+
+    let reveal = |plaintext: &str| GameEvent::RevealSolution {
+        hash: hash.clone(),
+        plaintext: plaintext.to_string(),
+    };
+
+    // A wrong plaintext is rejected.
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), reveal("Solution11"));
+    assert_eq!(ret, Err(ScienceError::RevealMismatch.into()));
+    // Only the owner may reveal.
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), reveal("Solution10"));
+    assert_eq!(ret, Err(ScienceError::NotSolutionOwner.into()));
+    assert!(game.revealed.is_empty());
+
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), reveal("Solution10"))?;
+    assert_eq!(game.revealed.get(&hash).map(String::as_str), Some("Solution10"));
+    Ok(())
+}
+
+#[test]
+fn test_init_seeded_solutions() -> anyhow::Result<()> {
+    let (_ctx, handler) = init_handler(AccountData {
+        encrypt_solutions: [("111", " player5 "), ("222", "alice")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        ..account_data()
+    })?;
+    let state: &ScienceGame = handler.get_state();
+
+    assert_eq!(state.seeded_count(), 2);
+    assert_eq!(state.encrypt_solutions.get("111").map(String::as_str), Some("player5"));
+    assert_eq!(state.encrypt_solutions.get("222").map(String::as_str), Some("alice"));
+    assert_eq!(state.players[0].local_encrypt_solutions, state.encrypt_solutions);
+
+    // A seed with an empty owner is rejected.
+    let ret = init_handler(AccountData {
+        encrypt_solutions: [("111".to_string(), " ".to_string())].into_iter().collect(),
+        ..account_data()
+    });
+    assert!(ret.is_err());
+    Ok(())
+}
+
+#[test]
+fn test_serialized_size() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    let initial_size = game.serialized_size()?;
+
+    evaluate(&mut game, "alice", "111")?;
+    let size = game.serialized_size()?;
+    assert!(size > initial_size);
+
+    evaluate(&mut game, "bob", "222")?;
+    assert!(game.serialized_size()? > size);
+    Ok(())
+}
+
+#[test]
+fn test_prune() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "alice", "222")?;
+    evaluate(&mut game, "bob", "333")?;
+
+    let prune = GameEvent::Prune(vec!["111".to_string(), "333".to_string()]);
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), prune);
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    assert_eq!(game.encrypt_solutions.len(), 3);
+
+    let prune = GameEvent::Prune(vec!["111".to_string(), "333".to_string()]);
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), prune)?;
+    assert_eq!(game.solutions_by("alice"), vec!["222".to_string()]);
+    assert!(game.solutions_by("bob").is_empty());
+    assert_eq!(game.players[1].local_encrypt_solutions, game.encrypt_solutions);
+    // Balances credited before pruning are untouched.
+    assert_eq!(game.players[0].balance, 2);
+    assert_eq!(game.players[1].balance, 1);
+    Ok(())
+}
+
+#[test]
+fn test_transactor_addr() -> anyhow::Result<()> {
+    let (_ctx, handler) = init_handler(account_data())?;
+    let state: &ScienceGame = handler.get_state();
+    assert_eq!(state.transactor_addr.as_deref(), Some("transactor"));
+
+    // Evaluations from anyone but the transactor are rejected.
+    let mut game = new_game(&["alice"]);
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "111".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    let ret = game.custom_handle_event(&mut Effect::default(), "server2".to_string(), GameEvent::Evaluate(message));
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    assert!(game.encrypt_solutions.is_empty());
+
+    // Until the transactor is known, privileged events are rejected from anyone.
+    game.transactor_addr = None;
+    let ret = game.custom_handle_event(&mut Effect::default(), "server2".to_string(), GameEvent::Pause);
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    assert!(!game.paused);
+    Ok(())
+}
+
+#[test]
+fn test_pending_duplicate_detection() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.recycle_fees = true;
+    game.submission_fee = 1;
+    fund(&mut game, &["bob"], 2);
+    evaluate(&mut game, "alice", "111")?;
+    submit(&mut game, "alice", b"blob-a")?;
+    submit(&mut game, "bob", b"blob-b")?;
+    submit(&mut game, "bob", b"blob-c")?;
+
+    let mark = |index: usize, hash: &str| GameEvent::MarkPendingHash {
+        index,
+        hash: hash.to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), mark(0, "222"))?;
+    assert_eq!(game.pending_hashes(), vec!["222".to_string()]);
+
+    // The same plaintext is already pending, so the second copy is dropped.
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), mark(1, "222"))?;
+    assert_eq!(game.tmp_solutions.len(), 2);
+    assert_eq!(game.tmp_solutions[1].payload, b"blob-c".to_vec());
+
+    // An already accepted hash is dropped as well.
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), mark(1, "111"))?;
+    assert_eq!(game.tmp_solutions.len(), 1);
+    assert_eq!(game.pending_hashes(), vec!["222".to_string()]);
+    // The fees of both dropped submissions are recycled.
+    assert_eq!(game.prize_pool, 2);
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), mark(5, "333"));
+    assert_eq!(ret, Err(ScienceError::UnknownPendingSubmission.into()));
+    Ok(())
+}
+
+#[test]
+fn test_heartbeat() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    let mut effect = Effect {
+        timestamp: 10_000,
+        ..Default::default()
+    };
+    game.custom_handle_event(&mut effect, "alice".to_string(), GameEvent::Heartbeat)?;
+    assert_eq!(game.players[0].last_seen, 10_000);
+    assert_eq!(game.players[1].last_seen, 0);
+
+    assert_eq!(game.players_seen_within(12_000, 5_000), vec!["alice".to_string()]);
+    assert!(game.players_seen_within(20_000, 5_000).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_verifier_reward_split() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    game.coin_assigned = 10;
+    game.verifier_share = 30;
+    let hash = hash_solution("Solution10");
+
+    evaluate(&mut game, "alice", &hash)?;
+    assert_eq!(game.players[0].balance, 7);
+
+    let verify = || GameEvent::Verify(hash.clone());
+    // Only revealed solutions can be verified.
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), verify());
+    assert_eq!(ret, Err(ScienceError::NotRevealed.into()));
+
+    let reveal = GameEvent::RevealSolution {
+        hash: hash.clone(),
+        plaintext: "Solution10".to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), reveal)?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), verify());
+    assert_eq!(ret, Err(ScienceError::SelfVerification.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), verify())?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "carol".to_string(), verify());
+    assert_eq!(ret, Err(ScienceError::AlreadyVerified.into()));
+
+    let credited: u64 = game.players.iter().map(|p| p.balance).sum();
+    assert_eq!(game.players[1].balance, 3);
+    assert_eq!(credited, game.coin_assigned);
+    assert_eq!(game.total_distributed(), game.coin_assigned);
+    Ok(())
+}
+
+#[test]
+fn test_problem_statement() -> anyhow::Result<()> {
+    let (_ctx, handler) = init_handler(AccountData {
+        problem_statement: "Prove the conjecture".to_string(),
+        ..account_data()
+    })?;
+    let state: &ScienceGame = handler.get_state();
+    assert_eq!(state.problem_statement(), "Prove the conjecture");
+
+    let ret = init_handler(AccountData {
+        problem_statement: "  ".to_string(),
+        ..account_data()
+    });
+    assert!(ret.is_err());
+    Ok(())
+}
+
+#[test]
+fn test_update_public_key() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    let mut rng = OsRng;
+    let private_key = RsaPrivateKey::new(&mut rng, 2048)?;
+    let public_key = RsaPublicKey::from(&private_key).to_public_key_pem()?;
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::UpdatePublicKey(public_key.clone()));
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::UpdatePublicKey("not a pem".to_string()));
+    assert_eq!(ret, Err(ScienceError::InvalidPublicKey.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::UpdatePublicKey(public_key.clone()))?;
+    assert_eq!(game.public_key, public_key);
+
+    // Clients encrypting with the published key can be decrypted with the new private key.
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "Solution10".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    let published_key = RsaPublicKey::from_public_key_pem(&game.public_key)?;
+    let encrypt_solution = encrypt_message(&message, &published_key, game.padding).expect("Failed to encrypt");
+    let decrypt_solution = decrypt_message(&encrypt_solution, &private_key, game.padding).expect("Failed to decrypt");
+    assert_eq!(decrypt_solution.content, "Solution10");
+    Ok(())
+}
+
+#[test]
+fn test_malformed_custom_event() {
+    let mut game = new_game(&["alice"]);
+    let event = Event::Custom {
+        sender: "alice".to_string(),
+        raw: vec![0xff, 0xfe, 0xfd],
+    };
+    let ret = game.handle_event(&mut Effect::default(), event);
+    assert_eq!(ret, Err(HandleError::Custom("malformed game event".to_string())));
+    assert!(game.tmp_solutions.is_empty());
+}
+
+#[test]
+fn test_min_players() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    fund(&mut game, &["alice"], 1);
+    game.min_players = 2;
+
+    assert_eq!(submit(&mut game, "alice", b"blob"), Err(ScienceError::NotEnoughPlayers.into()));
+    assert!(game.tmp_solutions.is_empty());
+
+    join(&mut game, "bob", 1)?;
+    submit(&mut game, "alice", b"blob")?;
+    assert_eq!(game.tmp_solutions.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_iter_solutions() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "bob", "222")?;
+
+    let mut entries: Vec<(&SolutionHash, &String)> = game.iter_solutions().collect();
+    entries.sort();
+    let expected = [("111", "alice"), ("222", "bob")];
+    assert_eq!(entries.len(), expected.len());
+    for ((hash, owner), (expected_hash, expected_owner)) in entries.into_iter().zip(expected) {
+        assert_eq!(hash.as_str(), expected_hash);
+        assert_eq!(owner, expected_owner);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_submission_fee() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.submission_fee = 2;
+    game.players[1].balance = 10;
+
+    // A broke player can't pay the fee.
+    assert_eq!(submit(&mut game, "alice", b"blob"), Err(ScienceError::InsufficientBalance.into()));
+    assert!(game.tmp_solutions.is_empty());
+
+    submit(&mut game, "bob", b"blob")?;
+    assert_eq!(game.players[1].balance, 8);
+
+    // The fee is refunded on acceptance, on top of the reward.
+    evaluate(&mut game, "bob", "111")?;
+    assert_eq!(game.players[1].balance, 11);
+
+    // A duplicate keeps the fee.
+    submit(&mut game, "bob", b"blob")?;
+    evaluate(&mut game, "bob", "111")?;
+    assert_eq!(game.players[1].balance, 9);
+
+    // Even free submissions take a non-zero balance.
+    game.submission_fee = 0;
+    assert_eq!(submit(&mut game, "alice", b"blob"), Err(ScienceError::InsufficientBalance.into()));
+    Ok(())
+}
+
+#[test]
+fn test_sync_only_touches_new_players() -> anyhow::Result<()> {
+    let addrs: Vec<String> = (0..50).map(|i| format!("player{}", i)).collect();
+    let addrs: Vec<&str> = addrs.iter().map(String::as_str).collect();
+    let mut game = new_game(&addrs);
+    evaluate(&mut game, "player0", "111")?;
+
+    // Mark every existing cache, a re-clone would overwrite the marker.
+    for player in game.players.iter_mut() {
+        player.local_encrypt_solutions.insert("marker".into(), player.addr.clone());
+    }
+
+    join(&mut game, "newcomer", 1)?;
+    // Replaying the same sync is a no-op.
+    join(&mut game, "newcomer", 1)?;
+
+    assert_eq!(game.players.len(), 51);
+    for player in &game.players[..50] {
+        assert!(player.local_encrypt_solutions.contains_key("marker"));
+    }
+    assert_eq!(game.players[50].local_encrypt_solutions, game.encrypt_solutions);
+    Ok(())
+}
+
+#[test]
+fn test_request_solution() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.solution_price = 4;
+    game.players[1].balance = 10;
+    let hash = hash_solution("Solution10");
+    evaluate(&mut game, "alice", &hash)?;
+
+    // Unrevealed solutions can't be purchased.
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::RequestSolution(hash.clone()));
+    assert_eq!(ret, Err(ScienceError::NotRevealed.into()));
+
+    let reveal = GameEvent::RevealSolution {
+        hash: hash.clone(),
+        plaintext: "Solution10".to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), reveal)?;
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::RequestSolution(hash.clone()))?;
+
+    assert_eq!(game.players[0].balance, 5);
+    assert_eq!(game.players[1].balance, 6);
+    assert_eq!(game.purchases.get(&hash), Some(&vec!["bob".to_string()]));
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::RequestSolution(hash.clone()));
+    assert_eq!(ret, Err(ScienceError::AlreadyPurchased.into()));
+
+    // A buyer who can't afford the price is rejected without any transfer.
+    game.purchases.clear();
+    game.players[1].balance = 3;
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::RequestSolution(hash));
+    assert_eq!(ret, Err(ScienceError::InsufficientBalance.into()));
+    assert_eq!(game.players[0].balance, 5);
+    Ok(())
+}
+
+#[test]
+fn test_total_submissions() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    submit(&mut game, "alice", b"blob-a")?;
+    submit(&mut game, "bob", b"blob-b")?;
+    submit(&mut game, "bob", b"blob-c")?;
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "bob", "222")?;
+    // The third one turns out to be a duplicate.
+    evaluate(&mut game, "bob", "111")?;
+
+    assert_eq!(game.total_submissions(), 3);
+    assert_eq!(game.total_distributed(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_check_invariants() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    game.check_invariants()?;
+    submit(&mut game, "alice", b"blob")?;
+    game.check_invariants()?;
+    evaluate(&mut game, "alice", "111")?;
+    game.check_invariants()?;
+
+    // A solution owned by someone who never joined, and wasn't seeded.
+    game.encrypt_solutions.insert("222".into(), "ghost".to_string());
+    assert_eq!(game.check_invariants(), Err(ScienceError::BrokenInvariant.into()));
+    game.seeded_hashes.insert("222".to_string());
+    game.check_invariants()?;
+
+    // Pending submissions while the stage says nothing was submitted.
+    submit(&mut game, "bob", b"blob")?;
+    game.stage = GameStage::Waiting;
+    assert_eq!(game.check_invariants(), Err(ScienceError::BrokenInvariant.into()));
+    game.stage = GameStage::Submitted;
+
+    // Balances that can't be summed.
+    game.players[0].balance = u64::MAX;
+    game.players[1].balance = 1;
+    assert_eq!(game.check_invariants(), Err(ScienceError::BrokenInvariant.into()));
+    Ok(())
+}
+
+#[test]
+fn test_expire_pending() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    let mut effect = Effect {
+        timestamp: 1_000,
+        ..Default::default()
+    };
+    game.custom_handle_event(&mut effect, "alice".to_string(), GameEvent::Submit { payload: b"blob-a".to_vec(), signature: Vec::new(), nonce: None })?;
+    effect.timestamp = 20_000;
+    game.custom_handle_event(&mut effect, "bob".to_string(), GameEvent::Submit { payload: b"blob-b".to_vec(), signature: Vec::new(), nonce: None })?;
+
+    // Alice's submission is now older than the action timeout, bob's isn't.
+    effect.timestamp = 1_000 + crate::ACTION_TIMEOUT + 1;
+    game.handle_event(&mut effect, Event::WaitingTimeout)?;
+    assert_eq!(game.tmp_solutions.len(), 1);
+    assert_eq!(game.tmp_solutions[0].sender, "bob");
+    assert_eq!(game.expired_submissions.len(), 1);
+    assert_eq!(game.expired_submissions[0].sender, "alice");
+    assert_eq!(
+        game.rejections,
+        vec![Rejection {
+            addr: "alice".to_string(),
+            code: ScienceError::SubmissionExpired.code(),
+        }]
+    );
+    assert_eq!(game.stage, GameStage::Submitted);
+
+    effect.timestamp = 20_000 + crate::ACTION_TIMEOUT + 1;
+    game.handle_event(&mut effect, Event::WaitingTimeout)?;
+    assert!(game.tmp_solutions.is_empty());
+    assert_eq!(game.stage, GameStage::Waiting);
+    Ok(())
+}
+
+#[test]
+fn test_vote() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    fund(&mut game, &["alice"], 1);
+    game.vote_quorum = 2;
+    let transactor = || "transactor".to_string();
+    let vote = |approve: bool| GameEvent::Vote {
+        hash: "111".to_string(),
+        approve,
+    };
+
+    submit(&mut game, "alice", b"blob")?;
+    let mark = GameEvent::MarkPendingHash {
+        index: 0,
+        hash: "111".to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), transactor(), mark)?;
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), vote(true));
+    assert_eq!(ret, Err(ScienceError::SelfVote.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), vote(true))?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), vote(true));
+    assert_eq!(ret, Err(ScienceError::AlreadyVoted.into()));
+    assert_eq!(game.votes.get("111"), Some(&(1, 0)));
+    assert_eq!(game.players[0].balance, 1);
+
+    // The second approval reaches the quorum and credits the submitter.
+    game.custom_handle_event(&mut Effect::default(), "carol".to_string(), vote(true))?;
+    assert_eq!(game.players[0].balance, 2);
+    assert_eq!(game.solutions_by("alice"), vec!["111".to_string()]);
+    assert!(game.tmp_solutions.is_empty());
+    assert!(game.votes.is_empty());
+    game.check_invariants()?;
+    Ok(())
+}
+
+#[test]
+fn test_stage_name() {
+    assert_eq!(GameStage::Waiting.stage_name(), "waiting");
+    assert_eq!(GameStage::Submitted.stage_name(), "submitted");
+    assert_eq!(GameStage::Evaluated.stage_name(), "evaluated");
+    assert_eq!(GameStage::Disputed.stage_name(), "disputed");
+    assert_eq!(serde_json::to_string(&GameStage::Submitted).unwrap(), "\"submitted\"");
+}
+
+#[test]
+fn test_max_stored_solutions() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    game.max_stored_solutions = 2;
+    evaluate(&mut game, "alice", "111")?;
+    submit(&mut game, "alice", b"blob")?;
+    evaluate(&mut game, "alice", "222")?;
+    assert!(game.storage_full());
+
+    // New submissions are turned away once the cap is reached.
+    assert_eq!(submit(&mut game, "alice", b"blob"), Err(ScienceError::StorageFull.into()));
+    assert_eq!(game.preview_evaluate("333", "alice", 1), EvaluateOutcome::StorageFull);
+    // A submission evaluated after the cap is reached is not stored.
+    evaluate(&mut game, "alice", "333")?;
+    assert_eq!(game.encrypt_solutions.len(), 2);
+    assert_eq!(game.players[0].balance, 2);
+
+    // Pruning makes room again.
+    let prune = GameEvent::Prune(vec!["111".to_string()]);
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), prune)?;
+    evaluate(&mut game, "alice", "333")?;
+    assert_eq!(game.solutions_by("alice"), vec!["222".to_string(), "333".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn test_reencrypt() -> anyhow::Result<()> {
+    let mut rng = OsRng;
+    let old_private_key = RsaPrivateKey::new(&mut rng, 2048)?;
+    let new_private_key = RsaPrivateKey::new(&mut rng, 2048)?;
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "Solution10".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    let blob = encrypt_message(&message, &RsaPublicKey::from(&old_private_key), Padding::OaepSha256).expect("Failed to encrypt");
+
+    let migrated = reencrypt(&old_private_key, &RsaPublicKey::from(&new_private_key), &blob, Padding::OaepSha256)?;
+    let decrypt_solution = decrypt_message(&migrated, &new_private_key, Padding::OaepSha256).expect("Failed to decrypt");
+    assert_eq!(decrypt_solution.sender, "alice");
+    assert_eq!(decrypt_solution.content, "Solution10");
+
+    // The old blob can't be migrated with the wrong private key.
+    let ret = reencrypt(&new_private_key, &RsaPublicKey::from(&new_private_key), &blob, Padding::OaepSha256);
+    assert_eq!(ret, Err(ScienceError::MalformedPayload.into()));
+    Ok(())
+}
+
+#[test]
+fn test_player_addrs() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    join(&mut game, "bob", 1)?;
+    join(&mut game, " carol ", 2)?;
+    assert_eq!(game.player_addrs(), vec!["alice".to_string(), "bob".to_string(), "carol".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn test_flag() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    let transactor = || "transactor".to_string();
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::Flag("alice".to_string()));
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+
+    game.custom_handle_event(&mut Effect::default(), transactor(), GameEvent::Flag("alice".to_string()))?;
+    assert!(game.players[0].flagged);
+    assert_eq!(submit(&mut game, "alice", b"blob"), Err(ScienceError::PlayerFlagged.into()));
+    submit(&mut game, "bob", b"blob")?;
+
+    game.custom_handle_event(&mut Effect::default(), transactor(), GameEvent::Unflag("alice".to_string()))?;
+    submit(&mut game, "alice", b"blob")?;
+    assert_eq!(game.tmp_solutions.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_identical_submissions_tie_break() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    let mut effect = Effect {
+        timestamp: 1_000,
+        ..Default::default()
+    };
+    // Bob's event arrives first, but both were submitted at the same time.
+    game.custom_handle_event(&mut effect, "bob".to_string(), GameEvent::Submit { payload: b"blob-b".to_vec(), signature: Vec::new(), nonce: None })?;
+    game.custom_handle_event(&mut effect, "alice".to_string(), GameEvent::Submit { payload: b"blob-a".to_vec(), signature: Vec::new(), nonce: None })?;
+    let senders: Vec<&str> = game.tmp_solutions.iter().map(|p| p.sender.as_str()).collect();
+    assert_eq!(senders, vec!["alice", "bob"]);
+
+    // The transactor evaluates in queue order, both decrypt to the same plaintext.
+    let hash = hash_solution("Solution10");
+    while let Some(front) = game.tmp_solutions.front() {
+        let solver = front.sender.clone();
+        evaluate(&mut game, &solver, &hash)?;
     }
+    assert_eq!(game.encrypt_solutions.get(hash.as_str()).map(String::as_str), Some("alice"));
+    assert_eq!(game.players[1].balance, 1);
+    Ok(())
+}
+
+#[test]
+fn test_accounting_summary() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 5;
+    // Deposits count towards the balances but were never distributed.
+    game.players[1].balance = 100;
+    assert_eq!(game.accounting_summary(), (100, 0));
+
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "bob", "222")?;
+    evaluate(&mut game, "alice", "333")?;
+    let (balances, distributed) = game.accounting_summary();
+    assert_eq!(distributed, 15);
+    assert_eq!(balances, 100 + distributed);
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_batch() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    game.coin_assigned = 3;
+    fund(&mut game, &["alice", "bob", "carol"], 1);
+    submit(&mut game, "alice", b"blob-a")?;
+    submit(&mut game, "bob", b"blob-b")?;
+    submit(&mut game, "carol", b"blob-c")?;
+    let batch = |entries: &[(&str, &str)]| {
+        GameEvent::EvaluateBatch(
+            entries
+                .iter()
+                .map(|(sender, hash)| Message {
+                    sender: sender.to_string(),
+                    content: hash.to_string(),
+                    difficulty: 1,
+                    full_hash: String::new(),
+                    references: Vec::new(),
+                    anonymous: false,
+                })
+                .collect(),
+        )
+    };
+
+    // An unknown sender fails the whole batch before anything is consumed.
+    let event = batch(&[("alice", "111"), ("mallory", "222")]);
+    assert_eq!(
+        game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), event),
+        Err(ScienceError::PlayerNotFound.into())
+    );
+    assert_eq!(game.tmp_solutions.len(), 3);
+    assert_eq!(game.total_distributed(), 0);
+
+    // Carol's solution duplicates Alice's and is skipped.
+    let event = batch(&[("alice", "111"), ("bob", "222"), ("carol", "111")]);
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), event)?;
+    assert!(game.tmp_solutions.is_empty());
+    assert_eq!(game.players[0].balance, 4);
+    assert_eq!(game.players[1].balance, 4);
+    assert_eq!(game.players[2].balance, 1);
+    assert_eq!(game.stage, GameStage::Waiting);
+    Ok(())
+}
+
+#[test]
+fn test_pause_resume() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    fund(&mut game, &["alice"], 1);
+    let pause = GameEvent::Pause;
+    assert_eq!(
+        game.custom_handle_event(&mut Effect::default(), "alice".to_string(), pause),
+        Err(ScienceError::NotTransactor.into())
+    );
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Pause)?;
+    assert_eq!(
+        submit(&mut game, "alice", b"blob"),
+        Err(HandleError::Custom("game paused".to_string()))
+    );
+    assert_eq!(evaluate(&mut game, "alice", "111"), Err(ScienceError::GamePaused.into()));
+    assert!(game.tmp_solutions.is_empty());
+
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Resume)?;
+    submit(&mut game, "alice", b"blob")?;
+    assert_eq!(game.tmp_solutions.len(), 1);
+    assert_eq!(game.stage, GameStage::Submitted);
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_sender_mismatch() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["bob"], 1);
+    // Bob submits a ciphertext whose embedded sender claims to be Alice.
+    submit(&mut game, "bob", b"blob")?;
+    assert_eq!(evaluate(&mut game, "alice", "111"), Err(ScienceError::SenderMismatch.into()));
+    assert!(game.encrypt_solutions.is_empty());
+    assert_eq!(game.players[0].balance, 0);
+    assert_eq!(game.tmp_solutions.len(), 1);
+
+    evaluate(&mut game, "bob", "111")?;
+    assert_eq!(game.encrypt_solutions.get("111").map(String::as_str), Some("bob"));
+    Ok(())
+}
+
+#[test]
+fn test_asset_id_settlement() -> anyhow::Result<()> {
+    let (mut ctx, mut handler) = init_handler(AccountData {
+        coin_assigned: 3,
+        asset_id: "USDC".to_string(),
+        ..account_data()
+    })?;
+    let transactor = TestClient::transactor("transactor");
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "111".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    handler.handle_event(&mut ctx, &transactor.custom_event(GameEvent::Evaluate(message)))?;
+    let state: &ScienceGame = handler.get_state();
+    assert_eq!(state.asset_id, "USDC");
+    let expected = Settlement {
+        addr: "alice".to_string(),
+        amount: 3,
+        asset_id: "USDC".to_string(),
+    };
+    assert_eq!(state.settlements, vec![expected]);
+
+    // Settlements only cover the latest event.
+    handler.handle_event(&mut ctx, &Event::WaitingTimeout)?;
+    let state: &ScienceGame = handler.get_state();
+    assert!(state.settlements.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_comments() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    evaluate(&mut game, "alice", "111")?;
+    let comment = |hash: &str, text: &str| GameEvent::Comment {
+        hash: hash.to_string(),
+        text: text.to_string(),
+    };
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), comment("111", "Nice proof"))?;
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), comment("111", "Thanks"))?;
+    assert_eq!(game.comments("111"), ["Nice proof".to_string(), "Thanks".to_string()]);
+    assert!(game.comments("222").is_empty());
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), comment("222", "Where?"));
+    assert_eq!(ret, Err(ScienceError::UnknownSolution.into()));
+    let long = "x".repeat(281);
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), comment("111", &long));
+    assert_eq!(ret, Err(ScienceError::CommentTooLong.into()));
+
+    for _ in 2..32 {
+        game.custom_handle_event(&mut Effect::default(), "bob".to_string(), comment("111", "+1"))?;
+    }
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), comment("111", "+1"));
+    assert_eq!(ret, Err(ScienceError::TooManyComments.into()));
+    assert_eq!(game.comments("111").len(), 32);
+    Ok(())
+}
+
+#[test]
+fn test_diverged_players() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    evaluate(&mut game, "alice", "111")?;
+    assert!(game.diverged_players().is_empty());
+
+    game.players[1].local_encrypt_solutions.clear();
+    assert_eq!(game.diverged_players(), vec!["bob".to_string()]);
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::Resync)?;
+    assert!(game.diverged_players().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_difficulty_scales_reward() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 2;
+    let evaluate_at = |game: &mut ScienceGame, sender: &str, hash: &str, difficulty: u8| {
+        let message = Message {
+            sender: sender.to_string(),
+            content: hash.to_string(),
+            difficulty,
+            full_hash: String::new(),
+            references: Vec::new(),
+            anonymous: false,
+        };
+        game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Evaluate(message))
+    };
+
+    evaluate_at(&mut game, "alice", "111", 5)?;
+    assert_eq!(game.players[0].balance, 10);
+    evaluate(&mut game, "bob", "222")?;
+    assert_eq!(game.players[1].balance, 2);
+
+    assert_eq!(evaluate_at(&mut game, "bob", "333", 0), Err(ScienceError::InvalidDifficulty.into()));
+    assert_eq!(evaluate_at(&mut game, "bob", "333", 11), Err(ScienceError::InvalidDifficulty.into()));
+    assert!(!game.encrypt_solutions.contains_key("333"));
+
+    // The scaled reward never exceeds the cap.
+    game.max_reward = 15;
+    assert_eq!(game.preview_evaluate("333", "bob", 10), EvaluateOutcome::WouldCredit(15));
+    evaluate_at(&mut game, "bob", "333", 10)?;
+    assert_eq!(game.players[1].balance, 17);
+    Ok(())
+}
+
+#[test]
+fn test_first_solver_bonus() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.first_solver_bonus = 10;
+    evaluate(&mut game, "bob", "111")?;
+    evaluate(&mut game, "alice", "222")?;
+    evaluate(&mut game, "bob", "333")?;
+    assert_eq!(game.first_solver.as_deref(), Some("bob"));
+    assert_eq!(game.players[0].balance, 1);
+    assert_eq!(game.players[1].balance, 12);
+    assert_eq!(game.total_distributed(), 13);
+    Ok(())
+}
+
+#[test]
+fn test_checkpoint_version() -> anyhow::Result<()> {
+    let checkpoint = new_game(&["alice"]).into_checkpoint()?;
+    let bytes = borsh::to_vec(&checkpoint)?;
+    assert_eq!(ScienceGameCheckpoint::restore(&bytes)?, checkpoint);
+
+    let bogus = borsh::to_vec(&ScienceGameCheckpoint { version: 999 })?;
+    assert_eq!(
+        ScienceGameCheckpoint::restore(&bogus),
+        Err(HandleError::Custom("unsupported checkpoint version".to_string()))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_solutions_since() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    for (timestamp, sender, hash) in [(1_000, "alice", "111"), (2_000, "bob", "222"), (3_000, "alice", "333")] {
+        let mut effect = Effect {
+            timestamp,
+            ..Default::default()
+        };
+        let message = Message {
+            sender: sender.to_string(),
+            content: hash.to_string(),
+            difficulty: 1,
+            full_hash: String::new(),
+            references: Vec::new(),
+            anonymous: false,
+        };
+        game.custom_handle_event(&mut effect, "transactor".to_string(), GameEvent::Evaluate(message))?;
+    }
+    assert_eq!(game.solutions_since(0).len(), 3);
+    assert_eq!(game.solutions_since(2_000), vec!["222".to_string(), "333".to_string()]);
+    assert!(game.solutions_since(3_001).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_broadcast_solutions() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    game.encrypt_solutions.insert("111".into(), "alice".to_string());
+    game.players[2].local_encrypt_solutions.insert("stale".into(), "carol".to_string());
+    assert_eq!(game.diverged_players().len(), 3);
+
+    game.broadcast_solutions();
+    assert!(game.diverged_players().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_transfer_ownership() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    evaluate(&mut game, "alice", "111")?;
+    let transfer = |to: &str| GameEvent::TransferOwnership {
+        hash: "111".to_string(),
+        to: to.to_string(),
+    };
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), transfer("bob"));
+    assert_eq!(ret, Err(ScienceError::NotSolutionOwner.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), transfer("mallory"));
+    assert_eq!(ret, Err(ScienceError::PlayerNotFound.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), transfer("bob"))?;
+    assert_eq!(game.encrypt_solutions.get("111").map(String::as_str), Some("bob"));
+    assert_eq!(game.solutions_by("alice"), Vec::<String>::new());
+    assert!(game.diverged_players().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_duplicate_penalty() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.duplicate_penalty = 3;
+    game.players[1].balance = 5;
+    evaluate(&mut game, "alice", "111")?;
+
+    submit(&mut game, "bob", b"blob")?;
+    evaluate(&mut game, "bob", "111")?;
+    assert_eq!(game.players[1].balance, 2);
+    assert_eq!(game.encrypt_solutions.get("111").map(String::as_str), Some("alice"));
+
+    // The penalty never takes the balance below zero.
+    evaluate(&mut game, "bob", "111")?;
+    assert_eq!(game.players[1].balance, 0);
+    Ok(())
+}
+
+#[test]
+fn test_solution_hash_keys() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    evaluate(&mut game, "alice", "111")?;
+    let key = SolutionHash::from("111");
+    assert_eq!(game.encrypt_solutions.get(&key).map(String::as_str), Some("alice"));
+    // Lookups by a plain str still work.
+    assert!(game.encrypt_solutions.contains_key("111"));
+    assert_eq!(key.to_string(), "111");
+    assert_eq!(String::from(key), "111".to_string());
+    Ok(())
+}
+
+#[test]
+fn test_recent_solutions() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    for (sender, hash) in [("alice", "555"), ("bob", "444"), ("alice", "333"), ("bob", "222"), ("alice", "111")] {
+        evaluate(&mut game, sender, hash)?;
+    }
+    let expected = vec![
+        ("333".to_string(), "alice".to_string()),
+        ("222".to_string(), "bob".to_string()),
+        ("111".to_string(), "alice".to_string()),
+    ];
+    assert_eq!(game.recent_solutions(3), expected);
+    assert_eq!(game.recent_solutions(10).len(), 5);
+
+    let prune = GameEvent::Prune(vec!["222".to_string()]);
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), prune)?;
+    assert_eq!(game.recent_solutions(2), vec![expected[0].clone(), expected[2].clone()]);
+    Ok(())
+}
+
+#[test]
+fn test_game_start() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    game.min_players = 2;
+    let start = || Event::GameStart { access_version: 1 };
+    assert_eq!(game.handle_event(&mut Effect::default(), start()), Err(ScienceError::NotEnoughPlayers.into()));
+    assert!(!game.started);
+
+    join(&mut game, "bob", 1)?;
+    game.handle_event(&mut Effect::default(), start())?;
+    assert!(game.started);
+    assert_eq!(game.stage, GameStage::Waiting);
+
+    fund(&mut game, &["bob"], 1);
+    // Restarting with a queued submission resumes in the submitted stage.
+    submit(&mut game, "bob", b"blob")?;
+    game.stage = GameStage::Waiting;
+    game.handle_event(&mut Effect::default(), start())?;
+    assert_eq!(game.stage, GameStage::Submitted);
+    Ok(())
+}
+
+#[test]
+fn test_shutdown() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.handle_event(&mut Effect::default(), Event::GameStart { access_version: 1 })?;
+    evaluate(&mut game, "alice", "111")?;
+    game.submission_fee = 2;
+    game.players[1].balance = 2;
+    submit(&mut game, "bob", b"blob")?;
+
+    game.handle_event(&mut Effect::default(), Event::Shutdown)?;
+    assert!(!game.started);
+    assert_eq!(game.stage, GameStage::Waiting);
+    assert!(game.tmp_solutions.is_empty());
+    assert_eq!(game.players[1].balance, 2);
+    assert!(game.encrypt_solutions.contains_key("111"));
+    Ok(())
+}
+
+#[test]
+fn test_rejection_codes() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["bob"], 1);
+    evaluate(&mut game, "alice", "111")?;
+    assert!(game.rejections.is_empty());
+
+    submit(&mut game, "bob", b"blob")?;
+    evaluate(&mut game, "bob", "111")?;
+    let expected = Rejection {
+        addr: "bob".to_string(),
+        code: ScienceError::DuplicateSolution.code(),
+    };
+    assert_eq!(game.rejections, vec![expected]);
+    // Codes are stable across releases.
+    assert_eq!(ScienceError::DuplicateSolution.code(), 1);
+
+    // Rejections only cover the latest event.
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+    assert!(game.rejections.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_multiple_evaluators() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.evaluators = vec!["evaluator1".to_string()];
+    let evaluate_as = |game: &mut ScienceGame, evaluator: &str, sender: &str, hash: &str| {
+        let message = Message {
+            sender: sender.to_string(),
+            content: hash.to_string(),
+            difficulty: 1,
+            full_hash: String::new(),
+            references: Vec::new(),
+            anonymous: false,
+        };
+        game.custom_handle_event(&mut Effect::default(), evaluator.to_string(), GameEvent::Evaluate(message))
+    };
+
+    let ret = evaluate_as(&mut game, "evaluator2", "bob", "222");
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    let add = GameEvent::AddEvaluator("evaluator2".to_string());
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), add);
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+
+    let add = GameEvent::AddEvaluator("evaluator2".to_string());
+    game.custom_handle_event(&mut Effect::default(), "evaluator1".to_string(), add)?;
+    evaluate_as(&mut game, "evaluator1", "alice", "111")?;
+    evaluate_as(&mut game, "evaluator2", "bob", "222")?;
+    assert_eq!(game.solutions_by("alice"), vec!["111".to_string()]);
+    assert_eq!(game.solutions_by("bob"), vec!["222".to_string()]);
+
+    let remove = GameEvent::RemoveEvaluator("evaluator1".to_string());
+    game.custom_handle_event(&mut Effect::default(), "evaluator2".to_string(), remove)?;
+    let ret = evaluate_as(&mut game, "evaluator1", "alice", "333");
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    Ok(())
+}
+
+#[test]
+fn test_rank_of() -> anyhow::Result<()> {
+    let mut game = new_game(&["carol", "bob", "alice"]);
+    game.players[0].balance = 5;
+    game.players[1].balance = 9;
+    game.players[2].balance = 5;
+    assert_eq!(
+        game.leaderboard(),
+        vec![("bob".to_string(), 9), ("alice".to_string(), 5), ("carol".to_string(), 5)]
+    );
+    assert_eq!(game.rank_of("bob"), Some(1));
+    assert_eq!(game.rank_of("alice"), Some(2));
+    assert_eq!(game.rank_of("carol"), Some(3));
+    assert_eq!(game.rank_of("mallory"), None);
+    Ok(())
+}
+
+#[test]
+fn test_old_account_data() -> anyhow::Result<()> {
+    // The account layout of the original release, before any field was appended.
+    let mut blob = borsh::to_vec(&7u64)?;
+    blob.extend(borsh::to_vec(&"pk".to_string())?);
+    blob.extend(borsh::to_vec(&HashMap::from([("111".to_string(), "alice".to_string())]))?);
+    let data = AccountData::try_from_slice(&blob)?;
+    assert_eq!(data.coin_assigned, 7);
+    assert_eq!(data.public_key, "pk");
+    assert_eq!(data.encrypt_solutions.get("111").map(String::as_str), Some("alice"));
+    assert_eq!(data.verifier_share, 0);
+    assert!(data.evaluators.is_empty());
+
+    let json = r#"{"coin_assigned": 7, "public_key": "pk", "encrypt_solutions": {}}"#;
+    let data: AccountData = serde_json::from_str(json)?;
+    assert_eq!(data.coin_assigned, 7);
+    assert_eq!(data.max_stored_solutions, 0);
+    assert!(data.asset_id.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_redeem() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.players[0].balance = 10;
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::Redeem(11));
+    assert_eq!(ret, Err(ScienceError::InsufficientBalance.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::Redeem(4))?;
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::Redeem(6))?;
+    assert_eq!(game.players[0].balance, 0);
+    let expected = vec![
+        Voucher {
+            addr: "alice".to_string(),
+            amount: 4,
+            id: voucher_id("alice", 0),
+        },
+        Voucher {
+            addr: "alice".to_string(),
+            amount: 6,
+            id: voucher_id("alice", 1),
+        },
+    ];
+    assert_eq!(game.vouchers, expected);
+    assert_eq!(voucher_id("alice", 0), full_hash_solution("alice:0"));
+    // Pinned, so an integration computing SHA-256 off chain gets the same id.
+    assert_eq!(voucher_id("alice", 0), "4afca6926e5ae3e5b19d7148f0c5b7fcb5859abae941cad4c0e6d3b605f2b3ca");
+
+    // Vouchers only cover the latest event.
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+    assert!(game.vouchers.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_max_pending() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    game.max_pending = 2;
+    submit(&mut game, "alice", b"blob-a")?;
+    submit(&mut game, "bob", b"blob-b")?;
+    assert_eq!(
+        submit(&mut game, "alice", b"blob-c"),
+        Err(HandleError::Custom("pending queue full".to_string()))
+    );
+    assert_eq!(game.tmp_solutions.len(), 2);
+
+    // Evaluating frees a slot.
+    evaluate(&mut game, "alice", "111")?;
+    submit(&mut game, "alice", b"blob-c")?;
+    assert_eq!(game.tmp_solutions.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_reset() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["bob"], 1);
+    evaluate(&mut game, "alice", "111")?;
+    submit(&mut game, "bob", b"blob")?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::Reset);
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Reset)?;
+    assert!(game.encrypt_solutions.is_empty());
+    assert!(game.tmp_solutions.is_empty());
+    assert_eq!(game.stage, GameStage::Waiting);
+    assert_eq!(game.player_addrs(), vec!["alice".to_string(), "bob".to_string()]);
+    assert!(game.players.iter().all(|p| p.balance == 0));
+    assert!(game.diverged_players().is_empty());
+    game.check_invariants()?;
+    Ok(())
+}
+
+#[test]
+fn test_accepted_broadcast() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 10;
+    game.verifier_share = 20;
+    fund(&mut game, &["bob"], 1);
+    submit(&mut game, "bob", b"blob")?;
+    evaluate(&mut game, "bob", "111")?;
+    let expected = AcceptedSolution {
+        hash: "111".to_string(),
+        solver: "bob".to_string(),
+        reward: 8,
+    };
+    assert_eq!(game.accepted, vec![expected]);
+    assert_eq!(game.players[1].balance, 9);
+
+    // Duplicates are not broadcast.
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+    evaluate(&mut game, "alice", "111")?;
+    assert!(game.accepted.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_zero_coin_assigned() -> anyhow::Result<()> {
+    let ret = init_handler(AccountData {
+        coin_assigned: 0,
+        ..account_data()
+    });
+    assert!(ret.is_err());
+    init_handler(account_data())?;
+    Ok(())
+}
+
+#[test]
+fn test_endorse() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    evaluate(&mut game, "alice", "111")?;
+    let endorse = || GameEvent::Endorse("111".to_string());
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), endorse())?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), endorse());
+    assert_eq!(ret, Err(ScienceError::AlreadyEndorsed.into()));
+    assert_eq!(game.endorsements("111"), 1);
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), endorse());
+    assert_eq!(ret, Err(ScienceError::SelfEndorsement.into()));
+    game.custom_handle_event(&mut Effect::default(), "carol".to_string(), endorse())?;
+    assert_eq!(game.endorsements("111"), 2);
+    assert_eq!(game.endorsements("222"), 0);
+    Ok(())
+}
+
+#[test]
+fn test_short_hash_collision() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    let evaluate_full = |game: &mut ScienceGame, sender: &str, plaintext: &str| {
+        // Pretend both plaintexts hash to the same short hash.
+        let message = Message {
+            sender: sender.to_string(),
+            content: "111".to_string(),
+            difficulty: 1,
+            full_hash: full_hash_solution(plaintext),
+            references: Vec::new(),
+            anonymous: false,
+        };
+        game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Evaluate(message))
+    };
+    assert_ne!(full_hash_solution("Solution10"), full_hash_solution("Solution11"));
+    assert_eq!(full_hash_solution("Solution10").len(), 64);
+
+    evaluate_full(&mut game, "alice", "Solution10")?;
+    evaluate_full(&mut game, "bob", "Solution11")?;
+    assert_eq!(game.solutions_by("alice"), vec!["111".to_string()]);
+    assert_eq!(game.solutions_by("bob"), vec![full_hash_solution("Solution11")]);
+    assert_eq!(game.players[1].balance, 1);
+
+    // Resubmitting either plaintext is still a duplicate.
+    evaluate_full(&mut game, "bob", "Solution10")?;
+    evaluate_full(&mut game, "alice", "Solution11")?;
+    assert_eq!(game.encrypt_solutions.len(), 2);
+    assert_eq!(game.players[0].balance, 1);
+    assert_eq!(game.players[1].balance, 1);
+
+    // The solution stored under its full hash can still be revealed.
+    let reveal = GameEvent::RevealSolution {
+        hash: full_hash_solution("Solution11"),
+        plaintext: "Solution11".to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), reveal)?;
+    assert!(game.revealed.contains_key(&full_hash_solution("Solution11")));
+    Ok(())
+}
+
+#[test]
+fn test_new_for_test() {
+    let players = vec![
+        Player {
+            addr: "alice".to_string(),
+            balance: 3,
+            ..Default::default()
+        },
+        Player {
+            addr: "bob".to_string(),
+            balance: 7,
+            ..Default::default()
+        },
+    ];
+    let game = ScienceGame::new_for_test(players, 2);
+    assert_eq!(game.coin_assigned, 2);
+    assert_eq!(game.stage, GameStage::Waiting);
+    assert_eq!(game.leaderboard(), vec![("bob".to_string(), 7), ("alice".to_string(), 3)]);
+}
+
+#[test]
+fn test_stage_display() {
+    assert_eq!(GameStage::Waiting.to_string(), "waiting");
+    assert_eq!(GameStage::Submitted.to_string(), "submitted");
+    assert_eq!(format!("stage: {}", GameStage::Evaluated), "stage: evaluated");
+}
+
+#[test]
+fn test_references() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    let evaluate_with = |game: &mut ScienceGame, hash: &str, references: Vec<String>| {
+        let message = Message {
+            sender: "alice".to_string(),
+            content: hash.to_string(),
+            difficulty: 1,
+            full_hash: String::new(),
+            references,
+            anonymous: false,
+        };
+        game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Evaluate(message))
+    };
+    let references = vec!["doi:10.1000/182".to_string(), "arXiv:1234.5678".to_string()];
+    evaluate_with(&mut game, "111", references.clone())?;
+    assert_eq!(game.references("111"), references.as_slice());
+    assert!(game.references("222").is_empty());
+
+    let too_many = (0..3).map(|i| format!("ref{}", i)).collect();
+    assert_eq!(evaluate_with(&mut game, "222", too_many), Err(ScienceError::TooManyReferences.into()));
+    assert!(!game.encrypt_solutions.contains_key("222"));
+    Ok(())
+}
+
+#[test]
+fn test_stale_pending() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    fund(&mut game, &["alice", "bob", "carol"], 1);
+    for (timestamp, sender) in [(1_000, "alice"), (5_000, "bob"), (9_000, "carol")] {
+        let mut effect = Effect {
+            timestamp,
+            ..Default::default()
+        };
+        game.custom_handle_event(&mut effect, sender.to_string(), GameEvent::Submit { payload: b"blob".to_vec(), signature: Vec::new(), nonce: None })?;
+    }
+    assert_eq!(game.stale_pending(10_000, 4_000), vec!["alice".to_string(), "bob".to_string()]);
+    assert_eq!(game.stale_pending(10_000, 9_000), Vec::<String>::new());
+    assert_eq!(game.stale_pending(10_000, 0).len(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_oaep_padding() -> anyhow::Result<()> {
+    let ret = init_handler(AccountData {
+        padding: "rot13".to_string(),
+        ..account_data()
+    });
+    assert!(ret.is_err());
+
+    let (_ctx, handler) = init_handler(AccountData {
+        padding: "oaep-sha256".to_string(),
+        ..account_data()
+    })?;
+    let state: &ScienceGame = handler.get_state();
+    assert_eq!(state.padding, Padding::OaepSha256);
+
+    let mut rng = OsRng;
+    let private_key = RsaPrivateKey::new(&mut rng, 2048)?;
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "Solution10".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    let blob = encrypt_message(&message, &RsaPublicKey::from(&private_key), state.padding).expect("Failed to encrypt");
+    // The transactor decrypts the queued submission with the game's padding.
+    let mut game = new_game(&["alice"]);
+    game.padding = state.padding;
+    fund(&mut game, &["alice"], 1);
+    submit(&mut game, "alice", &blob)?;
+    let decrypted = game.decrypt_pending(0, &private_key)?;
+    assert_eq!(decrypted.content, "Solution10");
+    // The ciphertext doesn't decrypt with the other padding.
+    assert!(decrypt_message(&blob, &private_key, Padding::Pkcs1v15).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_request_refund() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.submission_fee = 2;
+    game.players[0].balance = 5;
+    let refund = |game: &mut ScienceGame, now: u64| {
+        let mut effect = Effect {
+            timestamp: now,
+            ..Default::default()
+        };
+        game.custom_handle_event(&mut effect, "alice".to_string(), GameEvent::RequestRefund)
+    };
+
+    assert_eq!(refund(&mut game, 0), Err(ScienceError::NoExpiredSubmission.into()));
+    submit(&mut game, "alice", b"blob")?;
+    assert_eq!(game.players[0].balance, 3);
+    assert_eq!(refund(&mut game, 0), Err(ScienceError::StillPending.into()));
+
+    // Nobody evaluates the submission in time.
+    game.handle_event(&mut Effect { timestamp: 60_000, ..Default::default() }, Event::WaitingTimeout)?;
+    assert!(game.tmp_solutions.is_empty());
+    refund(&mut game, 60_000)?;
+    assert_eq!(game.players[0].balance, 5);
+    assert!(game.expired_submissions.is_empty());
+    assert_eq!(refund(&mut game, 60_000), Err(ScienceError::NoExpiredSubmission.into()));
+    Ok(())
+}
+
+#[test]
+fn test_has_evaluator() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    assert!(!game.has_evaluator());
+
+    let sync_event = Event::Sync {
+        new_players: vec![],
+        new_servers: vec![ServerJoin {
+            addr: "transactor".to_string(),
+            endpoint: "".into(),
+            access_version: 1,
+            verify_key: "".into(),
+        }],
+        transactor_addr: "transactor".to_string(),
+        access_version: 1,
+    };
+    game.handle_event(&mut Effect::default(), sync_event)?;
+    assert!(game.has_evaluator());
+
+    let leave = Event::ServerLeave {
+        server_addr: "transactor".to_string(),
+        transactor_addr: "server2".to_string(),
+    };
+    game.handle_event(&mut Effect::default(), leave)?;
+    assert!(!game.has_evaluator());
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_leaves_state_untouched_on_failure() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice"]);
+    fund(&mut game, &["alice"], 1);
+    game.first_solver_bonus = 10;
+    submit(&mut game, "alice", b"blob")?;
+    // The reward itself fits, but the bonus credited after it doesn't.
+    game.players[0].balance = u64::MAX - 1;
+
+    assert_eq!(evaluate(&mut game, "alice", "111"), Err(ScienceError::RewardOverflow.into()));
+    assert_eq!(game.players[0].balance, u64::MAX - 1);
+    assert_eq!(game.tmp_solutions.len(), 1);
+    assert_eq!(game.stage, GameStage::Submitted);
+    assert!(game.encrypt_solutions.is_empty());
+    assert!(game.first_solver.is_none());
+    assert_eq!(game.total_distributed(), 0);
+    assert!(game.settlements.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_anonymous_submission() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 4;
+    let message = Message {
+        sender: "bob".to_string(),
+        content: "111".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: true,
+    };
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), GameEvent::Evaluate(message))?;
+
+    assert_eq!(game.encrypt_solutions.get("111").map(String::as_str), Some(ANONYMOUS_OWNER));
+    assert!(game.solutions_by("bob").is_empty());
+    assert_eq!(game.accepted[0].solver, ANONYMOUS_OWNER);
+    assert_eq!(game.players[1].balance, 4);
+    game.check_invariants()?;
+
+    // Ownership checks go by the real solver, not the listed owner.
+    let endorse = GameEvent::Endorse("111".to_string());
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), endorse);
+    assert_eq!(ret, Err(ScienceError::SelfEndorsement.into()));
+    let transfer = |to: &str| GameEvent::TransferOwnership {
+        hash: "111".to_string(),
+        to: to.to_string(),
+    };
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), transfer("alice"));
+    assert_eq!(ret, Err(ScienceError::NotSolutionOwner.into()));
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), transfer("alice"))?;
+    assert_eq!(game.encrypt_solutions.get("111").map(String::as_str), Some(ANONYMOUS_OWNER));
+    assert_eq!(game.anonymous_owners.get("111").map(String::as_str), Some("alice"));
+
+    // Nobody can join as the anonymous owner.
+    assert_eq!(join(&mut game, ANONYMOUS_OWNER, 1), Err(ScienceError::ReservedAddress.into()));
+    game.check_invariants()?;
+    Ok(())
+}
+
+#[test]
+fn test_set_problem_statement() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 4;
+    evaluate(&mut game, "alice", "111")?;
+    game.submission_fee = 2;
+    game.players[1].balance = 2;
+    submit(&mut game, "bob", b"blob")?;
+    assert_eq!(game.players[1].balance, 0);
+
+    let set = |statement: &str| GameEvent::SetProblemStatement(statement.to_string());
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), set("new problem"));
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), set("  "));
+    assert_eq!(ret, Err(ScienceError::EmptyProblemStatement.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), set("new problem"))?;
+    assert_eq!(game.problem_statement(), "new problem");
+    assert_eq!(game.stage, GameStage::Waiting);
+    assert!(game.encrypt_solutions.is_empty());
+    assert!(game.tmp_solutions.is_empty());
+    assert_eq!(game.players[0].balance, 4);
+    assert_eq!(game.players[1].balance, 2);
+    game.check_invariants()?;
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_for_pem() -> anyhow::Result<()> {
+    let mut rng = OsRng;
+    let private_key = RsaPrivateKey::new(&mut rng, 2048)?;
+    let pem = RsaPublicKey::from(&private_key).to_public_key_pem()?;
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "Solution10".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    let blob = encrypt_for_pem(&message, &pem, Padding::Pkcs1v15)?;
+    let decrypt_solution = decrypt_message(&blob, &private_key, Padding::Pkcs1v15).expect("Failed to decrypt");
+    assert_eq!(decrypt_solution.content, "Solution10");
+    // An OAEP game gets ciphertext its transactor can decrypt.
+    let blob = encrypt_for_pem(&message, &pem, Padding::OaepSha256)?;
+    let decrypt_solution = decrypt_message(&blob, &private_key, Padding::OaepSha256).expect("Failed to decrypt");
+    assert_eq!(decrypt_solution.content, "Solution10");
+
+    let ret = encrypt_for_pem(&message, "not a pem", Padding::Pkcs1v15);
+    assert_eq!(ret, Err(ScienceError::InvalidPublicKey.into()));
+    Ok(())
+}
+
+#[test]
+fn test_set_nickname() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    let set = |nickname: &str| GameEvent::SetNickname(nickname.to_string());
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), set(" Curie "))?;
+    assert_eq!(game.players[0].nickname.as_deref(), Some("Curie"));
+    // Setting the same nickname again is fine.
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), set("Curie"))?;
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), set("curie"));
+    assert_eq!(ret, Err(ScienceError::NicknameTaken.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), set(""));
+    assert_eq!(ret, Err(ScienceError::InvalidNickname.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), set(&"x".repeat(33)));
+    assert_eq!(ret, Err(ScienceError::InvalidNickname.into()));
+    assert_eq!(game.players[1].nickname, None);
+    Ok(())
+}
+
+#[test]
+fn test_config() -> anyhow::Result<()> {
+    let (_ctx, handler) = init_handler(AccountData {
+        coin_assigned: 5,
+        submission_fee: 2,
+        max_pending: 8,
+        padding: "oaep-sha256".to_string(),
+        ..account_data()
+    })?;
+    let state: &ScienceGame = handler.get_state();
+    let config = state.config();
+    assert_eq!(
+        config,
+        GameConfig {
+            coin_assigned: 5,
+            problem_statement: "Find a novel solution".to_string(),
+            submission_fee: 2,
+            max_pending: 8,
+            padding: "oaep-sha256".to_string(),
+            action_timeout: 30_000,
+            next_game_timeout: 15_000,
+            ..Default::default()
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_sync_order() -> anyhow::Result<()> {
+    let player_join = |addr: &str, access_version: u64| PlayerJoin {
+        addr: addr.to_string(),
+        balance: 0,
+        position: 0,
+        access_version,
+        verify_key: "".into(),
+    };
+    let sync = |new_players: Vec<PlayerJoin>| Event::Sync {
+        new_players,
+        new_servers: vec![],
+        transactor_addr: "transactor".to_string(),
+        access_version: 3,
+    };
+    let mut game = new_game(&["alice"]);
+    let mut other = new_game(&["alice"]);
+    game.handle_event(
+        &mut Effect::default(),
+        sync(vec![player_join("dave", 3), player_join("carol", 2), player_join("bob", 2)]),
+    )?;
+    other.handle_event(
+        &mut Effect::default(),
+        sync(vec![player_join("bob", 2), player_join("dave", 3), player_join("carol", 2)]),
+    )?;
+
+    let expected: Vec<String> = ["alice", "bob", "carol", "dave"].iter().map(|a| a.to_string()).collect();
+    assert_eq!(game.player_addrs(), expected);
+    assert_eq!(other.player_addrs(), expected);
+    Ok(())
+}
+
+#[test]
+fn test_pending_submitter() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    for (ts, sender) in [(1_000, "bob"), (2_000, "alice"), (3_000, "bob")] {
+        let mut effect = Effect {
+            timestamp: ts,
+            ..Default::default()
+        };
+        game.custom_handle_event(&mut effect, sender.to_string(), GameEvent::Submit { payload: b"blob".to_vec(), signature: Vec::new(), nonce: None })?;
+    }
+
+    assert_eq!(game.pending_submitter(0), Some("bob"));
+    assert_eq!(game.pending_submitter(1), Some("alice"));
+    assert_eq!(game.pending_submitter(2), Some("bob"));
+    assert_eq!(game.pending_submitter(3), None);
+    Ok(())
+}
+
+#[test]
+fn test_fee_policy() -> anyhow::Result<()> {
+    for recycle_fees in [false, true] {
+        let mut game = new_game(&["alice", "bob"]);
+        game.recycle_fees = recycle_fees;
+        game.submission_fee = 2;
+        game.players[1].balance = 2;
+        evaluate(&mut game, "alice", "111")?;
+        submit(&mut game, "bob", b"blob")?;
+        // A duplicate forfeits the fee.
+        evaluate(&mut game, "bob", "111")?;
+        assert_eq!(game.players[1].balance, 0);
+        assert_eq!(game.prize_pool, if recycle_fees { 2 } else { 0 });
+
+        // The pool goes to the next accepted solution.
+        evaluate(&mut game, "alice", "222")?;
+        assert_eq!(game.players[0].balance, if recycle_fees { 4 } else { 2 });
+        assert_eq!(game.prize_pool, 0);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_public_view() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 3;
+    evaluate(&mut game, "bob", "222")?;
+    evaluate(&mut game, "alice", "111")?;
+
+    let view = game.public_view("alice");
+    assert_eq!(
+        view,
+        PublicView {
+            addr: "alice".to_string(),
+            balance: Some(3),
+            solutions: vec!["111".to_string(), "222".to_string()],
+            stage: GameStage::Evaluated,
+        }
+    );
+    // Neither bob nor the solution owners show up.
+    let json = serde_json::to_string(&view)?;
+    assert!(!json.contains("bob"));
+    assert!(!json.contains("local_encrypt_solutions"));
+
+    assert_eq!(game.public_view("carol").balance, None);
+    Ok(())
+}
+
+#[test]
+fn test_merge_solutions() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 3;
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "bob", "222")?;
+    let merge = |keep: &str, remove: &str| GameEvent::MergeSolutions {
+        keep: keep.to_string(),
+        remove: remove.to_string(),
+    };
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), merge("222", "111"));
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), merge("222", "222"));
+    assert_eq!(ret, Err(ScienceError::SameSolution.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), merge("222", "333"));
+    assert_eq!(ret, Err(ScienceError::UnknownSolution.into()));
+
+    // Keeping the later key still credits alice, who was first.
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), merge("222", "111"))?;
+    assert_eq!(game.players[0].balance, 6);
+    assert_eq!(game.players[1].balance, 0);
+    assert_eq!(game.encrypt_solutions.get("222").map(String::as_str), Some("alice"));
+    assert!(!game.encrypt_solutions.contains_key("111"));
+    assert_eq!(game.accepted_order, vec!["222".to_string()]);
+    game.check_invariants()?;
+
+    // Merging into a seeded solution moves no balance, its owner never joined. The kept solution
+    // counts as seeded from then on.
+    game.encrypt_solutions.insert("999".into(), "dave".to_string());
+    game.seeded_hashes.insert("999".to_string());
+    evaluate(&mut game, "bob", "333")?;
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), merge("333", "999"))?;
+    assert_eq!(game.players[1].balance, 3);
+    assert_eq!(game.encrypt_solutions.get("333").map(String::as_str), Some("dave"));
+    assert_eq!(game.accepted_order, vec!["222".to_string()]);
+    assert!(!game.accepted_at.contains_key("333"));
+    game.check_invariants()?;
+    Ok(())
+}
+
+#[test]
+fn test_unique_solvers() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    assert_eq!(game.unique_solvers(), 0);
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "alice", "222")?;
+    evaluate(&mut game, "bob", "333")?;
+    assert_eq!(game.unique_solvers(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_signed_submission() -> anyhow::Result<()> {
+    let mut game = new_game(&["bob"]);
+    fund(&mut game, &["bob"], 1);
+    let keypair = |seed: u8| {
+        let secret = SecretKey::from_bytes(&[seed; 32]).expect("Failed to build secret key");
+        let verify_key = VerifyKey::from(&secret);
+        (ExpandedSecretKey::from(&secret), verify_key)
+    };
+    let (secret, verify_key) = keypair(7);
+    let sign = |payload: &[u8]| secret.sign(payload, &verify_key).to_bytes().to_vec();
+    let submit_signed = |game: &mut ScienceGame, payload: &[u8], nonce: Option<u64>, signature: Vec<u8>| {
+        let event = GameEvent::Submit {
+            payload: payload.to_vec(),
+            signature,
+            nonce,
+        };
+        game.custom_handle_event(&mut Effect::default(), "alice".to_string(), event)
+    };
+
+    // Alice's key comes from the verify_key she joins with.
+    let hex: String = verify_key.to_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    let sync_event = Event::Sync {
+        new_players: vec![PlayerJoin {
+            addr: "alice".to_string(),
+            balance: 1,
+            position: 1,
+            access_version: 1,
+            verify_key: hex,
+        }],
+        new_servers: vec![],
+        transactor_addr: "transactor".to_string(),
+        access_version: 1,
+    };
+    game.handle_event(&mut Effect::default(), sync_event)?;
+    assert_eq!(game.players[1].signing_key, verify_key.to_bytes().to_vec());
+
+    submit_signed(&mut game, b"blob", Some(1), sign(&signed_submission(b"blob", 1)))?;
+    assert_eq!(game.tmp_solutions.len(), 1);
+
+    // A signature over another payload or nonce, or none at all, is rejected.
+    let ret = submit_signed(&mut game, b"blob", Some(2), sign(&signed_submission(b"other", 2)));
+    assert_eq!(ret, Err(ScienceError::InvalidSignature.into()));
+    let ret = submit_signed(&mut game, b"blob", Some(2), sign(&signed_submission(b"blob", 1)));
+    assert_eq!(ret, Err(ScienceError::InvalidSignature.into()));
+    let ret = submit_signed(&mut game, b"blob", Some(2), Vec::new());
+    assert_eq!(ret, Err(ScienceError::InvalidSignature.into()));
+    // Replays can't strip the nonce either.
+    let ret = submit_signed(&mut game, b"blob", None, sign(&signed_submission(b"blob", 1)));
+    assert_eq!(ret, Err(ScienceError::MissingNonce.into()));
+    assert_eq!(game.tmp_solutions.len(), 1);
+
+    // Players without a signing key can still submit unsigned, and register a first key.
+    submit(&mut game, "bob", b"blob")?;
+    assert_eq!(game.tmp_solutions.len(), 2);
+    let (_, other_key) = keypair(9);
+    let register = |key: &VerifyKey, signature: Vec<u8>| GameEvent::RegisterSigningKey {
+        key: key.to_bytes().to_vec(),
+        signature,
+    };
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), register(&other_key, Vec::new()))?;
+    assert_eq!(game.players[0].signing_key, other_key.to_bytes().to_vec());
+
+    // Rotating a key takes a signature by the old one over the player, their rotation count and
+    // the new key.
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), register(&other_key, Vec::new()));
+    assert_eq!(ret, Err(ScienceError::InvalidSignature.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), register(&other_key, sign(&other_key.to_bytes())));
+    assert_eq!(ret, Err(ScienceError::InvalidSignature.into()));
+    let signature = sign(&signing_key_rotation("alice", 0, &other_key.to_bytes()));
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), register(&other_key, signature.clone()))?;
+    assert_eq!(game.players[1].signing_key, other_key.to_bytes().to_vec());
+    assert_eq!(game.players[1].key_rotations, 1);
+    // A captured rotation can't be replayed.
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), register(&verify_key, signature));
+    assert_eq!(ret, Err(ScienceError::InvalidSignature.into()));
+
+    // A malformed verify_key is rejected on join instead of turning signing off.
+    let sync_event = Event::Sync {
+        new_players: vec![PlayerJoin {
+            addr: "carol".to_string(),
+            balance: 1,
+            position: 2,
+            access_version: 2,
+            verify_key: "not a key".to_string(),
+        }],
+        new_servers: vec![],
+        transactor_addr: "transactor".to_string(),
+        access_version: 2,
+    };
+    let ret = game.handle_event(&mut Effect::default(), sync_event);
+    assert_eq!(ret, Err(ScienceError::InvalidSigningKey.into()));
+    assert_eq!(game.player_addrs().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_solutions_with_prefix() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    for (hash, owner) in [("12345", "alice"), ("12399", "bob"), ("45123", "alice")] {
+        game.encrypt_solutions.insert(hash.into(), owner.to_string());
+    }
+    assert_eq!(game.solutions_with_prefix("123"), vec!["12345".to_string(), "12399".to_string()]);
+    assert_eq!(game.solutions_with_prefix("45"), vec!["45123".to_string()]);
+    assert!(game.solutions_with_prefix("9").is_empty());
+    assert_eq!(game.solutions_with_prefix("").len(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_round_history() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 2;
+    // Nothing was evaluated, so no round ends.
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+    assert!(game.round_history.is_empty());
+
+    evaluate(&mut game, "alice", "111")?;
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+    assert_eq!(game.stage, GameStage::Waiting);
+    evaluate(&mut game, "bob", "222")?;
+    evaluate(&mut game, "bob", "333")?;
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+
+    let round = |alice: u64, bob: u64| HashMap::from([("alice".to_string(), alice), ("bob".to_string(), bob)]);
+    assert_eq!(game.round_history, vec![round(2, 0), round(2, 4)]);
+
+    // Evaluating the last queued solution schedules the round end and no action timeout.
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "444".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    let mut effect = Effect::default();
+    game.custom_handle_event(&mut effect, "transactor".to_string(), GameEvent::Evaluate(message))?;
+    assert_eq!(game.stage, GameStage::Evaluated);
+    assert_eq!(effect.wait_timeout, Some(game.config().next_game_timeout));
+    assert!(effect.action_timeout.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_start_grace() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    game.coin_assigned = 1;
+    game.first_solver_bonus = 10;
+    game.start_grace_ms = 5_000;
+    let at = |timestamp: u64| Effect {
+        timestamp,
+        ..Default::default()
+    };
+    game.handle_event(&mut at(1_000), Event::GameStart { access_version: 1 })?;
+
+    // Submitted within the grace window, evaluated after it: still no bonus.
+    game.custom_handle_event(&mut at(2_000), "alice".to_string(), GameEvent::Submit { payload: b"blob".to_vec(), signature: Vec::new(), nonce: None })?;
+    let message = Message {
+        sender: "alice".to_string(),
+        content: "111".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    game.custom_handle_event(&mut at(7_000), "transactor".to_string(), GameEvent::Evaluate(message))?;
+    assert_eq!(game.players[0].balance, 2);
+    assert_eq!(game.first_solver, None);
+
+    // The first solution after the window gets it.
+    game.custom_handle_event(&mut at(7_000), "bob".to_string(), GameEvent::Submit { payload: b"blob".to_vec(), signature: Vec::new(), nonce: None })?;
+    let message = Message {
+        sender: "bob".to_string(),
+        content: "222".to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    game.custom_handle_event(&mut at(8_000), "transactor".to_string(), GameEvent::Evaluate(message))?;
+    assert_eq!(game.players[1].balance, 12);
+    assert_eq!(game.first_solver.as_deref(), Some("bob"));
+    Ok(())
+}
+
+#[test]
+fn test_unrevealed_solutions() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    let hash = hash_solution("Solution10");
+    evaluate(&mut game, "alice", &hash)?;
+    evaluate(&mut game, "bob", "222")?;
+    let reveal = GameEvent::RevealSolution {
+        hash: hash.clone(),
+        plaintext: "Solution10".to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), reveal)?;
+
+    assert_eq!(game.unrevealed_solutions(), vec!["222".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn test_legacy_message() -> anyhow::Result<()> {
+    let mut rng = OsRng;
+    let private_key = RsaPrivateKey::new(&mut rng, 2048)?;
+    let public_key = RsaPublicKey::from(&private_key);
+    // A ciphertext of a message from before difficulty and the other claims were added
+    let legacy = br#"{"sender":"alice","content":"111"}"#;
+    let blob = public_key.encrypt(&mut rng, PaddingScheme::new_pkcs1v15_encrypt(), legacy)?;
+    let message = decrypt_message(&blob, &private_key, Padding::Pkcs1v15).expect("Failed to decrypt");
+    assert_eq!(message.sender, "alice");
+    assert_eq!(message.content, "111");
+    assert_eq!(message.difficulty, 1);
+    assert!(message.full_hash.is_empty());
+    assert!(message.references.is_empty());
+    assert!(!message.anonymous);
+    // Claims left at their default aren't serialized, so the payload stays as small as before.
+    assert_eq!(serde_json::to_string(&message)?.as_bytes(), legacy);
+    Ok(())
+}
+
+#[test]
+fn test_crypto_errors() -> anyhow::Result<()> {
+    let mut rng = OsRng;
+    let private_key = RsaPrivateKey::new(&mut rng, 2048)?;
+    let public_key = RsaPublicKey::from(&private_key);
+
+    let ret = decrypt_message(b"not a ciphertext", &private_key, Padding::Pkcs1v15);
+    assert_eq!(ret.err(), Some(CryptoError::Decryption));
+
+    // Decrypts fine, but isn't a serialized message.
+    let blob = public_key.encrypt(&mut rng, PaddingScheme::new_pkcs1v15_encrypt(), b"not json")?;
+    let ret = decrypt_message(&blob, &private_key, Padding::Pkcs1v15);
+    assert_eq!(ret.err(), Some(CryptoError::Deserialization));
+    assert_eq!(HandleError::from(CryptoError::Deserialization), ScienceError::MalformedPayload.into());
+    Ok(())
+}
+
+#[test]
+fn test_request_hint() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.hints = vec!["Think small".to_string(), "Try primes".to_string()];
+    game.hint_price = 2;
+    game.players[0].balance = 5;
+    let delivery = |hint: &str| HintDelivery {
+        addr: "alice".to_string(),
+        hint: hint.to_string(),
+    };
+
+    // Through handle_event, so the deliveries are replaced on every event.
+    let request_hint = || Event::Custom {
+        sender: "alice".to_string(),
+        raw: borsh::to_vec(&GameEvent::RequestHint).expect("Failed to serialize event"),
+    };
+    game.handle_event(&mut Effect::default(), request_hint())?;
+    assert_eq!(game.hints_delivered, vec![delivery("Think small")]);
+    game.handle_event(&mut Effect::default(), request_hint())?;
+    assert_eq!(game.hints_delivered, vec![delivery("Try primes")]);
+    assert_eq!(game.players[0].balance, 1);
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::RequestHint);
+    assert_eq!(ret, Err(ScienceError::NoHintLeft.into()));
+    // Bob starts from the first hint, but can't pay for it.
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::RequestHint);
+    assert_eq!(ret, Err(ScienceError::InsufficientBalance.into()));
+    assert_eq!(game.players[1].hints_used, 0);
+    Ok(())
+}
+
+#[test]
+fn test_acceptance_rate() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    assert_eq!(game.acceptance_rate(), 0.0);
+    submit(&mut game, "alice", b"blob")?;
+    submit(&mut game, "bob", b"blob")?;
+    submit(&mut game, "alice", b"blob")?;
+    // Queued by (submitted_at, sender), so both of alice's come first.
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "alice", "222")?;
+    evaluate(&mut game, "bob", "111")?;
+
+    assert!((game.acceptance_rate() - 2.0 / 3.0).abs() < 1e-9);
+    Ok(())
+}
+
+#[test]
+fn test_allowed_viewers() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    game.solution_price = 2;
+    game.players[1].balance = 5;
+    game.players[2].balance = 5;
+    let hash = hash_solution("Solution10");
+    evaluate(&mut game, "alice", &hash)?;
+    let reveal = GameEvent::RevealSolution {
+        hash: hash.clone(),
+        plaintext: "Solution10".to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), reveal)?;
+    let restrict = GameEvent::SetAllowedViewers {
+        hash: hash.clone(),
+        viewers: Some(vec!["bob".to_string()]),
+    };
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), restrict);
+    assert_eq!(ret, Err(ScienceError::NotSolutionOwner.into()));
+    let restrict = GameEvent::SetAllowedViewers {
+        hash: hash.clone(),
+        viewers: Some(vec!["bob".to_string()]),
+    };
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), restrict)?;
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), GameEvent::RequestSolution(hash.clone()))?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "carol".to_string(), GameEvent::RequestSolution(hash));
+    assert_eq!(ret, Err(ScienceError::NotAllowedViewer.into()));
+    assert_eq!(game.players[1].balance, 3);
+    assert_eq!(game.players[2].balance, 5);
+    Ok(())
+}
+
+#[test]
+fn test_kick() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    game.recycle_fees = true;
+    game.submission_fee = 1;
+    game.players[1].balance = 3;
+    game.players[2].balance = 2;
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "bob", "222")?;
+    submit(&mut game, "alice", b"blob")?;
+    submit(&mut game, "bob", b"blob")?;
+
+    let kick = |addr: &str| GameEvent::Kick(addr.to_string());
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), kick("bob"));
+    assert_eq!(ret, Err(ScienceError::NotTransactor.into()));
+    game.settlements.clear();
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), kick("bob"))?;
+
+    assert_eq!(game.player_addrs(), vec!["alice".to_string(), "carol".to_string()]);
+    assert_eq!(game.pending_submitter(0), Some("alice"));
+    assert_eq!(game.pending_submitter(1), None);
+    // Bob's fee went to the pool and his balance is settled to him, his solution stays.
+    assert_eq!(game.prize_pool, 1);
+    assert_eq!(
+        game.settlements,
+        vec![Settlement {
+            addr: "bob".to_string(),
+            amount: 3,
+            asset_id: String::new(),
+        }]
+    );
+    assert_eq!(game.solutions_by("bob"), vec!["222".to_string()]);
+    game.check_invariants()?;
+
+    game.forfeit_kicked_balance = true;
+    game.settlements.clear();
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), kick("carol"))?;
+    assert_eq!(game.prize_pool, 3);
+    assert!(game.settlements.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_replayed_submission() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    fund(&mut game, &["bob"], 1);
+    game.submission_fee = 1;
+    game.players[0].balance = 5;
+    let submit_once = |nonce: u64| GameEvent::Submit {
+        payload: b"blob".to_vec(),
+        signature: Vec::new(),
+        nonce: Some(nonce),
+    };
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), submit_once(1))?;
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), submit_once(1))?;
+    assert_eq!(game.tmp_solutions.len(), 1);
+    assert_eq!(game.players[0].balance, 4);
+
+    // Nonces are per sender.
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), submit_once(2))?;
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), submit_once(1))?;
+    assert_eq!(game.tmp_solutions.len(), 3);
+
+    // Nonces must increase, an older one is a replay too.
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), submit_once(5))?;
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), submit_once(3))?;
+    assert_eq!(game.tmp_solutions.len(), 4);
+    assert_eq!(game.players[0].last_nonce, 5);
+    Ok(())
+}
+
+#[test]
+fn test_to_csv() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.coin_assigned = 2;
+    evaluate(&mut game, "bob", "222")?;
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "alice", "333")?;
+
+    let csv = game.to_csv();
+    let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[0], vec!["hash", "owner", "balance"]);
+    assert_eq!(rows[1], vec!["111", "alice", "4"]);
+    assert_eq!(rows[2], vec!["222", "bob", "2"]);
+    Ok(())
+}
+
+#[test]
+fn test_weighted_vote() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    fund(&mut game, &["alice"], 1);
+    game.vote_weight_threshold = 10;
+    game.players[1].balance = 3;
+    game.players[2].balance = 12;
+    let vote = |approve: bool| GameEvent::Vote {
+        hash: "111".to_string(),
+        approve,
+    };
+    submit(&mut game, "alice", b"blob")?;
+    let mark = GameEvent::MarkPendingHash {
+        index: 0,
+        hash: "111".to_string(),
+    };
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), mark)?;
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), vote(true))?;
+    assert_eq!(game.vote_weights.get("111"), Some(&(3, 0)));
+    assert_eq!(game.tmp_solutions.len(), 1);
+
+    // Carol's balance alone meets the threshold.
+    game.custom_handle_event(&mut Effect::default(), "carol".to_string(), vote(false))?;
+    assert!(game.tmp_solutions.is_empty());
+    assert!(game.vote_weights.is_empty());
+    assert!(game.solutions_by("alice").is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_dispute() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    let challenge = |hash: &str| GameEvent::Challenge(hash.to_string());
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), challenge("111"));
+    assert_eq!(ret, Err(ScienceError::NotChallengeable.into()));
+    evaluate(&mut game, "alice", "111")?;
+    evaluate(&mut game, "alice", "222")?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), challenge("111"));
+    assert_eq!(ret, Err(ScienceError::SelfChallenge.into()));
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), challenge("111"))?;
+    assert_eq!(game.stage, GameStage::Disputed);
+    // The round is on hold until the challenge is resolved.
+    let ret = submit(&mut game, "bob", b"blob");
+    assert_eq!(ret, Err(ScienceError::SolutionDisputed.into()));
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+    assert_eq!(game.stage, GameStage::Disputed);
+
+    let resolve = |upheld: bool| GameEvent::ResolveChallenge { upheld };
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), resolve(false))?;
+    assert_eq!(game.stage, GameStage::Evaluated);
+    assert_eq!(game.solutions_by("alice").len(), 2);
+
+    game.custom_handle_event(&mut Effect::default(), "bob".to_string(), challenge("111"))?;
+    game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), resolve(true))?;
+    assert_eq!(game.stage, GameStage::Evaluated);
+    assert_eq!(game.solutions_by("alice"), vec!["222".to_string()]);
+    let ret = game.custom_handle_event(&mut Effect::default(), "transactor".to_string(), resolve(true));
+    assert_eq!(ret, Err(ScienceError::NoDispute.into()));
+    // The dropped solution stays reserved, so it isn't accepted and rewarded again.
+    assert_eq!(game.preview_evaluate("111", "bob", 1), EvaluateOutcome::Duplicate);
+
+    // Only solutions accepted in the current round can be challenged.
+    game.handle_event(&mut Effect::default(), Event::WaitingTimeout)?;
+    evaluate(&mut game, "bob", "333")?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "bob".to_string(), challenge("222"));
+    assert_eq!(ret, Err(ScienceError::NotChallengeable.into()));
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), challenge("333"))?;
+    assert_eq!(game.stage, GameStage::Disputed);
+    game.check_invariants()?;
+    Ok(())
+}
+
+#[test]
+fn test_pending_per_player() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob", "carol"]);
+    fund(&mut game, &["alice", "bob"], 1);
+    submit(&mut game, "alice", b"blob-a")?;
+    submit(&mut game, "bob", b"blob-b")?;
+    submit(&mut game, "alice", b"blob-c")?;
+
+    let counts = game.pending_per_player();
+    assert_eq!(counts, HashMap::from([("alice".to_string(), 2), ("bob".to_string(), 1)]));
+    Ok(())
+}
+
+#[test]
+fn test_evict_when_full() -> anyhow::Result<()> {
+    // Without eviction, a player who joined on chain is still admitted.
+    let mut game = new_game(&["alice", "bob"]);
+    game.max_players = 2;
+    join(&mut game, "carol", 1)?;
+    assert_eq!(game.player_addrs().len(), 3);
+
+    let mut game = new_game(&["alice", "bob"]);
+    game.max_players = 2;
+    game.evict_when_full = true;
+    game.submission_fee = 1;
+    game.players[0].balance = 5;
+    game.players[1].balance = 4;
+    evaluate(&mut game, "bob", "222")?;
+    submit(&mut game, "bob", b"blob")?;
+    game.settlements.clear();
+    join(&mut game, "carol", 1)?;
+
+    assert_eq!(game.player_addrs(), vec!["alice".to_string(), "carol".to_string()]);
+    // Bob's balance and pending fee are settled to him, and his solution stays.
+    assert_eq!(
+        game.settlements,
+        vec![Settlement {
+            addr: "bob".to_string(),
+            amount: 5,
+            asset_id: String::new(),
+        }]
+    );
+    assert_eq!(game.solutions_by("bob"), vec!["222".to_string()]);
+    assert!(game.tmp_solutions.is_empty());
+    game.check_invariants()?;
+    Ok(())
+}
+
+#[test]
+fn test_all_caches_synced() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    evaluate(&mut game, "alice", "111")?;
+    assert!(game.all_caches_synced());
+
+    game.players[1].local_encrypt_solutions.clear();
+    assert!(!game.all_caches_synced());
+    Ok(())
+}
+
+#[test]
+fn test_donate() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "bob"]);
+    game.players[0].balance = 5;
+    game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::Donate(3))?;
+    assert_eq!(game.players[0].balance, 2);
+    assert_eq!(game.prize_pool, 3);
+
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::Donate(3));
+    assert_eq!(ret, Err(ScienceError::InsufficientBalance.into()));
+    let ret = game.custom_handle_event(&mut Effect::default(), "alice".to_string(), GameEvent::Donate(0));
+    assert_eq!(ret, Err(ScienceError::InvalidAmount.into()));
+
+    // The pool goes to the next accepted solution.
+    evaluate(&mut game, "bob", "111")?;
+    assert_eq!(game.players[1].balance, 4);
+    assert_eq!(game.prize_pool, 0);
+    Ok(())
+}
+
+#[test]
+fn test_evaluator_fee() -> anyhow::Result<()> {
+    let mut game = new_game(&["alice", "carol"]);
+    game.evaluators = vec!["carol".to_string()];
+    game.evaluator_fee = 2;
+    game.prize_pool = 3;
+    let message = |content: &str| Message {
+        sender: "alice".to_string(),
+        content: content.to_string(),
+        difficulty: 1,
+        full_hash: String::new(),
+        references: Vec::new(),
+        anonymous: false,
+    };
+    game.custom_handle_event(&mut Effect::default(), "carol".to_string(), GameEvent::Evaluate(message("111")))?;
+    assert_eq!(game.players[1].balance, 2);
+    // The solver collects what is left of the pool.
+    assert_eq!(game.players[0].balance, 2);
+    assert_eq!(game.prize_pool, 0);
 
+    // Nothing left in the pool to pay the evaluator with.
+    game.custom_handle_event(&mut Effect::default(), "carol".to_string(), GameEvent::Evaluate(message("222")))?;
+    assert_eq!(game.players[1].balance, 2);
 
+    // A fee the evaluator can't absorb fails the evaluation before anything changes.
+    game.prize_pool = 3;
+    game.players[1].balance = u64::MAX - 1;
+    submit(&mut game, "alice", b"blob")?;
+    let ret = game.custom_handle_event(&mut Effect::default(), "carol".to_string(), GameEvent::Evaluate(message("333")));
+    assert_eq!(ret, Err(ScienceError::RewardOverflow.into()));
+    assert_eq!(game.tmp_solutions.len(), 1);
+    assert_eq!(game.prize_pool, 3);
     Ok(())
-}
\ No newline at end of file
+}